@@ -0,0 +1,214 @@
+use crate::{canvas::Canvas, waveform::Waveform};
+use glam::DVec2;
+use wasm_bindgen::JsValue;
+
+/// Height of the line with the shown ticks above the traces.
+const HEADER_HEIGHT: f64 = 18.0;
+const ROW_HEIGHT: f64 = 22.0;
+/// Width of the column with the names of the probes left of the traces.
+const NAMES_WIDTH: f64 = 140.0;
+
+/// The logic analyzer panel showing the recorded signals of the probes as
+/// traces over time.
+pub struct Analyzer {
+    pub open: bool,
+    /// Number of ticks across the width of the traces.
+    span: f64,
+    /// The tick at the right edge, `None` to follow the newest sample.
+    end: Option<f64>,
+}
+
+impl Analyzer {
+    pub fn new() -> Self {
+        Self {
+            open: true,
+            span: 64.0,
+            end: None,
+        }
+    }
+    /// Top left corner and size of the panel with its bottom edge at `bottom`,
+    /// `None` if it is closed or there is nothing to show.
+    pub fn bounds(&self, waveform: &Waveform, screen: DVec2, bottom: f64) -> Option<[DVec2; 2]> {
+        if !self.open || waveform.probes().is_empty() {
+            return None;
+        }
+        let height = HEADER_HEIGHT + ROW_HEIGHT * waveform.probes().len() as f64;
+        let pos = DVec2::new(10.0, bottom - height);
+        Some([pos, DVec2::new(screen.x - 20.0, height)])
+    }
+    /// The first and last tick shown.
+    fn range(&self, waveform: &Waveform) -> [f64; 2] {
+        let newest = waveform
+            .ticks()
+            .map_or(0.0, |[_, newest]| newest as f64 + 1.0);
+        let end = self.end.unwrap_or(newest).max(self.span);
+        [end - self.span, end]
+    }
+    /// Left edge and width of the traces.
+    fn traces([pos, size]: [DVec2; 2]) -> [f64; 2] {
+        [pos.x + NAMES_WIDTH, size.x - NAMES_WIDTH]
+    }
+    fn x_of(&self, waveform: &Waveform, bounds: [DVec2; 2], tick: f64) -> f64 {
+        let [start, _] = self.range(waveform);
+        let [left, width] = Self::traces(bounds);
+        left + (tick - start) / self.span * width
+    }
+    /// The tick under the screen position, if it is over the traces.
+    pub fn tick_at(&self, waveform: &Waveform, bounds: [DVec2; 2], pos: DVec2) -> Option<u64> {
+        let [left, width] = Self::traces(bounds);
+        if pos.x < left || pos.x > left + width || !contains(bounds, pos) {
+            return None;
+        }
+        let [start, _] = self.range(waveform);
+        Some(
+            (start + (pos.x - left) / width * self.span)
+                .floor()
+                .max(0.0) as u64,
+        )
+    }
+    /// The probe whose trace is at the screen position.
+    pub fn probe_at(&self, waveform: &Waveform, bounds: [DVec2; 2], pos: DVec2) -> Option<usize> {
+        if !contains(bounds, pos) {
+            return None;
+        }
+        let row = (pos.y - bounds[0].y - HEADER_HEIGHT) / ROW_HEIGHT;
+        (row >= 0.0 && (row as usize) < waveform.probes().len()).then_some(row as usize)
+    }
+    /// Zooms in for factors below one, keeping the tick at the screen position
+    /// in place.
+    pub fn zoom(&mut self, waveform: &Waveform, bounds: [DVec2; 2], pos: DVec2, factor: f64) {
+        let [start, end] = self.range(waveform);
+        let [left, width] = Self::traces(bounds);
+        let along = ((pos.x - left) / width).clamp(0.0, 1.0);
+        let tick = start + along * self.span;
+        self.span = (self.span * factor).clamp(4.0, 1e7);
+        let new_end = tick + (1.0 - along) * self.span;
+        // keep following the newest sample if it stays at the right edge
+        if self.end.is_some() || new_end < end {
+            self.end = Some(new_end);
+        }
+    }
+    /// Moves the shown ticks by a fraction of the width.
+    pub fn pan(&mut self, waveform: &Waveform, amount: f64) {
+        let [_, end] = self.range(waveform);
+        self.end = Some(end + amount * self.span);
+    }
+    /// Shows the newest samples from now on.
+    pub fn follow(&mut self) {
+        self.end = None;
+    }
+    /// Draws the panel with a cursor at the mouse, a marker at the current
+    /// tick of the grid and the hovered trace highlighted.
+    pub fn draw(
+        &self,
+        canvas: &Canvas,
+        waveform: &Waveform,
+        bounds: [DVec2; 2],
+        mouse: DVec2,
+        tick: u64,
+    ) {
+        let [pos, size] = bounds;
+        let [start, end] = self.range(waveform);
+        let [left, width] = Self::traces(bounds);
+        canvas.set_fill_style(&JsValue::from("#1b1b1b"));
+        canvas.fill_rect(pos.x, pos.y, size.x, size.y);
+        canvas.set_font("12px monospace");
+        canvas.set_text_baseline("middle");
+        canvas.set_text_align("left");
+        canvas.set_fill_style(&JsValue::from("#aaaaaa"));
+        let header = pos.y + HEADER_HEIGHT / 2.0;
+        canvas
+            .fill_text(&format!("{}", start.max(0.0) as u64), left, header)
+            .unwrap();
+        canvas.set_text_align("right");
+        canvas
+            .fill_text(&format!("{}", end as u64), left + width, header)
+            .unwrap();
+
+        let cursor = self.tick_at(waveform, bounds, mouse);
+        let hovered = self.probe_at(waveform, bounds, mouse);
+        for (i, probe) in waveform.probes().iter().enumerate() {
+            let top = pos.y + HEADER_HEIGHT + i as f64 * ROW_HEIGHT;
+            if hovered == Some(i) {
+                canvas.set_fill_style(&JsValue::from("#2e2e2e"));
+                canvas.fill_rect(pos.x, top, size.x, ROW_HEIGHT);
+            }
+            let value = match cursor.and_then(|tick| waveform.value_at(i, tick)) {
+                Some(active) => format!(" = {}", active as u8),
+                None => String::new(),
+            };
+            canvas.set_fill_style(&JsValue::from("white"));
+            canvas.set_text_align("left");
+            canvas
+                .fill_text(
+                    &format!("{}{value}", probe.name),
+                    pos.x + 6.0,
+                    top + ROW_HEIGHT / 2.0,
+                )
+                .unwrap();
+            self.draw_trace(canvas, waveform, bounds, i, top);
+        }
+
+        let marker = self.x_of(waveform, bounds, tick as f64);
+        if (left..=left + width).contains(&marker) {
+            let top = DVec2::new(marker, pos.y + HEADER_HEIGHT);
+            canvas.draw_line("yellow", top, DVec2::new(marker, pos.y + size.y));
+        }
+        if let Some(cursor) = cursor {
+            let x = mouse.x;
+            canvas.draw_line(
+                "white",
+                DVec2::new(x, pos.y + HEADER_HEIGHT),
+                DVec2::new(x, pos.y + size.y),
+            );
+            canvas.set_fill_style(&JsValue::from("white"));
+            canvas.set_text_align("center");
+            canvas.fill_text(&format!("{cursor}"), x, header).unwrap();
+        }
+    }
+    /// The signal as a line at the top of the row while active and at the
+    /// bottom while not, unknown parts are left out.
+    fn draw_trace(
+        &self,
+        canvas: &Canvas,
+        waveform: &Waveform,
+        bounds: [DVec2; 2],
+        probe: usize,
+        top: f64,
+    ) {
+        let [start, end] = self.range(waveform);
+        let [left, width] = Self::traces(bounds);
+        let level = |active: bool| match active {
+            true => top + 4.0,
+            false => top + ROW_HEIGHT - 4.0,
+        };
+        let samples: Vec<(u64, Option<bool>)> = waveform
+            .samples_between(probe, start.max(0.0) as u64, end as u64)
+            .collect();
+        canvas.begin_path();
+        canvas.set_stroke_style(&JsValue::from("lime"));
+        let mut last: Option<bool> = None;
+        for (i, &(tick, active)) in samples.iter().enumerate() {
+            let until = samples.get(i + 1).map_or(tick + 1, |(next, _)| *next);
+            let from_x = self.x_of(waveform, bounds, tick as f64).max(left);
+            let to_x = self.x_of(waveform, bounds, until as f64).min(left + width);
+            match (last, active) {
+                (_, None) => {}
+                (Some(_), Some(active)) => {
+                    canvas.line_to(from_x, level(active));
+                    canvas.line_to(to_x, level(active));
+                }
+                (None, Some(active)) => {
+                    canvas.move_to(from_x, level(active));
+                    canvas.line_to(to_x, level(active));
+                }
+            }
+            last = active;
+        }
+        canvas.stroke();
+    }
+}
+
+fn contains([pos, size]: [DVec2; 2], p: DVec2) -> bool {
+    pos.x <= p.x && pos.y <= p.y && p.x <= pos.x + size.x && p.y <= pos.y + size.y
+}
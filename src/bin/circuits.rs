@@ -0,0 +1,445 @@
+use circuits3::{
+    Cell, Counterexample, Equivalence, EquivalenceCheck, EquivalenceError, Grid, Settle,
+    TruthTable, TruthTableError, Waveform,
+};
+use std::{io::Read, process::ExitCode};
+
+const USAGE: &str = "\
+usage: circuits <SAVE> [OPTIONS]
+
+Loads a saved grid, simulates it and prints the result.
+SAVE is a file containing either the raw bytes of a save, the text of the
+`save` URL parameter (optionally percent encoded) or a whole URL.
+Use `-` to read from stdin.
+
+options:
+    --ticks <N>            simulate N ticks (default 0)
+    --until-stable [MAX]   simulate until no cell changes anymore, at most MAX
+                           ticks (default 10000), fails listing the changing
+                           cells if the grid oscillates or if it never settles
+    --set <X>,<Y>=<0|1>    hold the cell at X,Y active or inactive on every tick
+    --pin <NAME>=<0|1>     hold the input pin NAME active or inactive
+    --cell <X>,<Y>         print the state of the cell at X,Y instead of the grid
+    --output <NAME>        print the state of the output pin NAME instead of the
+                           grid
+    --probe <NAME>=<X>,<Y> record the cell at X,Y as NAME for --vcd
+    --vcd <FILE>           write the signals of the probes on every tick to FILE
+                           as a Value Change Dump, all pins are probed if there
+                           are no probes
+    --truth-table <csv|md> print the truth table of all pins as CSV or Markdown
+                           instead of simulating, settling every row for at
+                           most the MAX of --until-stable ticks
+    --equivalent <OTHER>   check that the save OTHER with the same pins computes
+                           the same outputs for every input vector instead of
+                           simulating, fails with the first counterexample
+    --vectors <N>          check N random input vectors with --equivalent if
+                           there are more than 16 inputs (default 10000)
+    --seed <N>             seed of the random input vectors (default 1)
+    -h, --help             print this message
+";
+
+#[derive(Debug)]
+enum Error {
+    Usage(String),
+    Io(std::io::Error),
+    Vcd(std::io::Error),
+    Parse(circuits3::GridParseError),
+    OutOfBounds([usize; 2]),
+    NotSettable([usize; 2]),
+    UnknownPin(String),
+    Unstable(u64),
+    Oscillating { period: u64, cells: Vec<[usize; 2]> },
+    TruthTable(TruthTableError),
+    Equivalence(EquivalenceError),
+    NotEquivalent(Counterexample),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Usage(msg) => write!(f, "{msg}\n\n{USAGE}"),
+            Error::Io(e) => write!(f, "failed to read save: {e}"),
+            Error::Vcd(e) => write!(f, "failed to write VCD: {e}"),
+            Error::Parse(e) => write!(f, "failed to parse save: {e:?}"),
+            Error::OutOfBounds([x, y]) => write!(f, "cell {x},{y} is outside of the grid"),
+            Error::NotSettable([x, y]) => write!(f, "cell {x},{y} has no output to set"),
+            Error::UnknownPin(name) => write!(f, "there is no pin named `{name}`"),
+            Error::Unstable(ticks) => write!(f, "grid did not settle within {ticks} ticks"),
+            Error::Oscillating { period, cells } => {
+                write!(
+                    f,
+                    "grid oscillates with a period of {period} ticks, changing cells:"
+                )?;
+                for [x, y] in cells {
+                    write!(f, " {x},{y}")?;
+                }
+                Ok(())
+            }
+            Error::TruthTable(TruthTableError::TooManyInputs(count)) => write!(
+                f,
+                "{count} input pins are too many for a truth table, at most {} are supported",
+                TruthTable::MAX_INPUTS
+            ),
+            Error::TruthTable(e) => write!(f, "failed to make the truth table: {e:?}"),
+            Error::Equivalence(EquivalenceError::DifferentInputs { only_a, only_b })
+            | Error::Equivalence(EquivalenceError::DifferentOutputs { only_a, only_b }) => {
+                write!(f, "the pins of the saves differ")?;
+                if !only_a.is_empty() {
+                    write!(f, ", only the first has {}", only_a.join(", "))?;
+                }
+                if !only_b.is_empty() {
+                    write!(f, ", only the second has {}", only_b.join(", "))?;
+                }
+                Ok(())
+            }
+            Error::NotEquivalent(counterexample) => {
+                write!(f, "the saves differ for")?;
+                for (name, active) in &counterexample.inputs {
+                    write!(f, " {name}={}", *active as u8)?;
+                }
+                let value = |value: Option<bool>| match value {
+                    None => "unsettled",
+                    Some(false) => "0",
+                    Some(true) => "1",
+                };
+                for mismatch in &counterexample.outputs {
+                    write!(
+                        f,
+                        "\n  {} is {} in the first and {} in the second",
+                        mismatch.name,
+                        value(mismatch.a),
+                        value(mismatch.b)
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    Csv,
+    Markdown,
+}
+
+#[derive(Debug, Default)]
+struct Args {
+    save: Option<String>,
+    ticks: u64,
+    until_stable: Option<u64>,
+    set: Vec<([usize; 2], bool)>,
+    pins: Vec<(String, bool)>,
+    cells: Vec<[usize; 2]>,
+    outputs: Vec<String>,
+    probes: Vec<(String, [usize; 2])>,
+    vcd: Option<String>,
+    truth_table: Option<Format>,
+    equivalent: Option<String>,
+    vectors: Option<u64>,
+    seed: Option<u64>,
+    help: bool,
+}
+
+fn parse_pos(s: &str) -> Result<[usize; 2], Error> {
+    let invalid = || Error::Usage(format!("invalid cell position `{s}`, expected X,Y"));
+    let (x, y) = s.split_once(',').ok_or_else(invalid)?;
+    let x = x.trim().parse().map_err(|_| invalid())?;
+    let y = y.trim().parse().map_err(|_| invalid())?;
+    Ok([x, y])
+}
+
+fn parse_state(state: &str) -> Result<bool, Error> {
+    match state.trim() {
+        "0" => Ok(false),
+        "1" => Ok(true),
+        state => Err(Error::Usage(format!("invalid input state `{state}`"))),
+    }
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Args, Error> {
+    let mut parsed = Args::default();
+    let mut args = args.peekable();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| Error::Usage(format!("missing value for {name}")))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => parsed.help = true,
+            "--ticks" => {
+                let ticks = value("--ticks")?;
+                parsed.ticks = ticks
+                    .parse()
+                    .map_err(|_| Error::Usage(format!("invalid tick count `{ticks}`")))?;
+            }
+            "--until-stable" => {
+                let max = match args.peek() {
+                    Some(max) if !max.starts_with('-') => {
+                        let max = args.next().unwrap();
+                        max.parse()
+                            .map_err(|_| Error::Usage(format!("invalid tick count `{max}`")))?
+                    }
+                    _ => 10000,
+                };
+                parsed.until_stable = Some(max);
+            }
+            "--set" => {
+                let set = value("--set")?;
+                let (pos, state) = set.split_once('=').ok_or_else(|| {
+                    Error::Usage(format!("invalid input `{set}`, expected X,Y=0|1"))
+                })?;
+                parsed.set.push((parse_pos(pos)?, parse_state(state)?));
+            }
+            "--pin" => {
+                let pin = value("--pin")?;
+                let (name, state) = pin.split_once('=').ok_or_else(|| {
+                    Error::Usage(format!("invalid input `{pin}`, expected NAME=0|1"))
+                })?;
+                parsed.pins.push((name.to_string(), parse_state(state)?));
+            }
+            "--cell" => {
+                let pos = value("--cell")?;
+                parsed.cells.push(parse_pos(&pos)?);
+            }
+            "--output" => parsed.outputs.push(value("--output")?),
+            "--probe" => {
+                let probe = value("--probe")?;
+                let (name, pos) = probe.split_once('=').ok_or_else(|| {
+                    Error::Usage(format!("invalid probe `{probe}`, expected NAME=X,Y"))
+                })?;
+                parsed.probes.push((name.to_string(), parse_pos(pos)?));
+            }
+            "--vcd" => parsed.vcd = Some(value("--vcd")?),
+            "--truth-table" => {
+                let format = value("--truth-table")?;
+                parsed.truth_table = Some(match format.as_str() {
+                    "csv" => Format::Csv,
+                    "md" => Format::Markdown,
+                    _ => {
+                        return Err(Error::Usage(format!(
+                            "invalid truth table format `{format}`, expected csv or md"
+                        )))
+                    }
+                });
+            }
+            "--equivalent" => parsed.equivalent = Some(value("--equivalent")?),
+            "--vectors" => {
+                let vectors = value("--vectors")?;
+                parsed.vectors = Some(
+                    vectors
+                        .parse()
+                        .map_err(|_| Error::Usage(format!("invalid vector count `{vectors}`")))?,
+                );
+            }
+            "--seed" => {
+                let seed = value("--seed")?;
+                parsed.seed = Some(
+                    seed.parse()
+                        .map_err(|_| Error::Usage(format!("invalid seed `{seed}`")))?,
+                );
+            }
+            _ if arg.starts_with("--") => {
+                return Err(Error::Usage(format!("unknown option `{arg}`")));
+            }
+            _ if parsed.save.is_none() => parsed.save = Some(arg),
+            _ => return Err(Error::Usage(format!("unexpected argument `{arg}`"))),
+        }
+    }
+    Ok(parsed)
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                decoded.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                decoded.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn load(bytes: Vec<u8>) -> Result<Grid, Error> {
+    let text = match std::str::from_utf8(&bytes) {
+        Ok(text) => text.trim(),
+        Err(_) => return Grid::deserialize(bytes.into_iter()).map_err(Error::Parse),
+    };
+    let text = match text.split_once("save=") {
+        Some((_, save)) => save.split('&').next().unwrap_or_default(),
+        None => text,
+    };
+    Grid::from_save_string(&percent_decode(text))
+        .or_else(|_| Grid::from_save_string(text))
+        .or_else(|e| Grid::deserialize(bytes.iter().copied()).map_err(|_| e))
+        .map_err(Error::Parse)
+}
+
+fn hold(grid: &mut Grid, args: &Args) -> Result<(), Error> {
+    for &([x, y], state) in &args.set {
+        let cell = grid.get(x, y).ok_or(Error::OutOfBounds([x, y]))?;
+        if matches!(cell, Cell::Empty | Cell::Point { .. }) {
+            return Err(Error::NotSettable([x, y]));
+        }
+        grid.set_active(x, y, state);
+    }
+    for (name, state) in &args.pins {
+        if !grid.set_input(name, *state) {
+            return Err(Error::UnknownPin(name.clone()));
+        }
+    }
+    Ok(())
+}
+
+fn tick(grid: &mut Grid, args: &Args, waveform: &mut Waveform) -> Result<(), Error> {
+    grid.simulate_incremental();
+    hold(grid, args)?;
+    waveform.sample(grid);
+    Ok(())
+}
+
+fn truth_table(grid: &Grid, max_ticks: u64, format: Format) -> Result<(), Error> {
+    let table = TruthTable::of_pins(grid, max_ticks).map_err(Error::TruthTable)?;
+    match format {
+        Format::Csv => print!("{}", table.csv()),
+        Format::Markdown => print!("{}", table.markdown()),
+    }
+    let unsettled = table.unsettled().count();
+    if unsettled > 0 {
+        eprintln!("warning: {unsettled} rows have outputs that never settle, shown as x:");
+        for row in table.unsettled() {
+            let inputs = (table.inputs.iter().zip(&row.inputs))
+                .map(|(name, active)| format!("{name}={}", *active as u8));
+            let outputs = (table.outputs.iter().zip(&row.outputs))
+                .filter(|(_, active)| active.is_none())
+                .map(|(name, _)| name.as_str());
+            eprintln!(
+                "  {} -> {}",
+                inputs.collect::<Vec<_>>().join(" "),
+                outputs.collect::<Vec<_>>().join(" ")
+            );
+        }
+    }
+    Ok(())
+}
+
+fn read(path: &str) -> Result<Vec<u8>, Error> {
+    match path {
+        "-" => {
+            let mut bytes = Vec::new();
+            std::io::stdin()
+                .read_to_end(&mut bytes)
+                .map_err(Error::Io)?;
+            Ok(bytes)
+        }
+        path => std::fs::read(path).map_err(Error::Io),
+    }
+}
+
+fn equivalent(grid: &Grid, other: &Grid, args: &Args) -> Result<(), Error> {
+    let default = EquivalenceCheck::default();
+    let check = EquivalenceCheck {
+        max_ticks: args.until_stable.unwrap_or(default.max_ticks),
+        random_vectors: args.vectors.unwrap_or(default.random_vectors),
+        seed: args.seed.unwrap_or(default.seed),
+        ..default
+    };
+    match check.run(grid, other).map_err(Error::Equivalence)? {
+        Equivalence::Equal {
+            vectors,
+            exhaustive: true,
+        } => println!("equivalent for all {vectors} input vectors"),
+        Equivalence::Equal {
+            vectors,
+            exhaustive: false,
+        } => println!("equivalent for {vectors} random input vectors"),
+        Equivalence::Different(counterexample) => return Err(Error::NotEquivalent(counterexample)),
+    }
+    Ok(())
+}
+
+fn run(args: Args) -> Result<(), Error> {
+    let Some(save) = args.save.as_deref() else {
+        return Err(Error::Usage(String::from("missing save")));
+    };
+    let mut grid = load(read(save)?)?;
+    if let Some(format) = args.truth_table {
+        return truth_table(&grid, args.until_stable.unwrap_or(10000), format);
+    }
+    if let Some(other) = &args.equivalent {
+        let other = load(read(other)?)?;
+        return equivalent(&grid, &other, &args);
+    }
+    hold(&mut grid, &args)?;
+    let mut waveform = Waveform::new();
+    for (name, [x, y]) in &args.probes {
+        grid.get(*x, *y).ok_or(Error::OutOfBounds([*x, *y]))?;
+        waveform.add_probe(name, [*x, *y]);
+    }
+    if args.probes.is_empty() && args.vcd.is_some() {
+        for (pos, _, name) in grid.pins() {
+            waveform.add_probe(name, pos);
+        }
+    }
+    waveform.sample(&grid);
+    for _ in 0..args.ticks {
+        tick(&mut grid, &args, &mut waveform)?;
+    }
+    if let Some(max) = args.until_stable {
+        // the inputs were checked by the first `hold`
+        let settle = grid.settle_with(max, |grid| tick(grid, &args, &mut waveform).unwrap());
+        if let Some(path) = &args.vcd {
+            std::fs::write(path, waveform.vcd()).map_err(Error::Vcd)?;
+        }
+        match settle {
+            Settle::Stable { ticks } => eprintln!("stable after {ticks} ticks"),
+            Settle::Oscillating { period, cells, .. } => {
+                return Err(Error::Oscillating { period, cells })
+            }
+            Settle::Unsettled => return Err(Error::Unstable(max)),
+        }
+    } else if let Some(path) = &args.vcd {
+        std::fs::write(path, waveform.vcd()).map_err(Error::Vcd)?;
+    }
+    if args.cells.is_empty() && args.outputs.is_empty() {
+        print!("{grid}");
+    }
+    for [x, y] in args.cells {
+        let cell = grid.get(x, y).ok_or(Error::OutOfBounds([x, y]))?;
+        println!("{x},{y}: {}", cell.is_active() as u8);
+    }
+    for name in args.outputs {
+        let active = grid.output(&name).ok_or(Error::UnknownPin(name.clone()))?;
+        println!("{name}: {}", active as u8);
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let result = parse_args(std::env::args().skip(1)).and_then(|args| match args.help {
+        true => {
+            print!("{USAGE}");
+            Ok(())
+        }
+        false => run(args),
+    });
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -62,11 +62,27 @@ impl Canvas {
         self.canvas.set_height(self.height);
     }
     pub fn draw_image(&self, image: &Image, pos: DVec2, size: DVec2, angle: f64, alpha: f64) {
+        self.draw_image_flipped(image, pos, size, angle, alpha, false);
+    }
+    /// Like [`Canvas::draw_image`], mirrors the image horizontally before
+    /// rotating it if `flipped` is set.
+    pub fn draw_image_flipped(
+        &self,
+        image: &Image,
+        pos: DVec2,
+        size: DVec2,
+        angle: f64,
+        alpha: f64,
+        flipped: bool,
+    ) {
         self.ctx.save();
         self.ctx
             .translate(pos.x + size.x * 0.5, pos.y + size.y * 0.5)
             .unwrap();
         self.ctx.rotate(angle).unwrap();
+        if flipped {
+            self.ctx.scale(-1.0, 1.0).unwrap();
+        }
         self.ctx.set_global_alpha(alpha);
         self.ctx
             .draw_image_with_html_image_element_and_sw_and_sh_and_dx_and_dy_and_dw_and_dh(
@@ -0,0 +1,68 @@
+use crate::grid::Cell;
+use std::collections::BTreeMap;
+
+/// Width and height of a chunk in cells.
+pub(crate) const CHUNK_SIZE: usize = 16;
+
+pub(crate) type Chunk = [Cell; CHUNK_SIZE * CHUNK_SIZE];
+
+/// The cells of a grid, stored in square chunks which are only allocated
+/// when one of their cells is written. Cells of missing chunks are empty.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Chunks {
+    /// Keyed by the row and column of the chunk, so they are ordered row by
+    /// row.
+    chunks: BTreeMap<[usize; 2], Box<Chunk>>,
+}
+
+static EMPTY: Cell = Cell::Empty;
+
+/// The key of the chunk containing a cell and the index of the cell in it.
+fn split([x, y]: [usize; 2]) -> ([usize; 2], usize) {
+    (
+        [y / CHUNK_SIZE, x / CHUNK_SIZE],
+        x % CHUNK_SIZE + y % CHUNK_SIZE * CHUNK_SIZE,
+    )
+}
+
+impl Chunks {
+    pub fn get(&self, pos: [usize; 2]) -> &Cell {
+        let (key, i) = split(pos);
+        self.chunks.get(&key).map_or(&EMPTY, |chunk| &chunk[i])
+    }
+    /// Allocates the chunk of the cell if it doesn't exist yet.
+    pub fn get_mut(&mut self, pos: [usize; 2]) -> &mut Cell {
+        let (key, i) = split(pos);
+        &mut self
+            .chunks
+            .entry(key)
+            .or_insert_with(|| Box::new([Cell::Empty; CHUNK_SIZE * CHUNK_SIZE]))[i]
+    }
+    /// Like [`Chunks::get_mut`], but `None` instead of allocating a chunk.
+    pub fn get_mut_allocated(&mut self, pos: [usize; 2]) -> Option<&mut Cell> {
+        let (key, i) = split(pos);
+        self.chunks.get_mut(&key).map(|chunk| &mut chunk[i])
+    }
+    /// The allocated chunks with the position of their top left cell.
+    pub fn iter(&self) -> impl Iterator<Item = ([usize; 2], &Chunk)> {
+        self.chunks
+            .iter()
+            .map(|([row, column], chunk)| ([column * CHUNK_SIZE, row * CHUNK_SIZE], &**chunk))
+    }
+    /// The first row of chunks at or below the given row, as the row and its
+    /// chunks with their columns.
+    pub fn row(&self, from: usize) -> Option<(usize, Vec<(usize, &Chunk)>)> {
+        let (&[row, _], _) = self.chunks.range([from, 0]..).next()?;
+        let chunks = self
+            .chunks
+            .range([row, 0]..[row + 1, 0])
+            .map(|([_, column], chunk)| (*column, &**chunk))
+            .collect();
+        Some((row, chunks))
+    }
+    /// Frees the chunks without any non-empty cells.
+    pub fn shrink(&mut self) {
+        self.chunks
+            .retain(|_, chunk| chunk.iter().any(|cell| *cell != Cell::Empty));
+    }
+}
@@ -0,0 +1,213 @@
+use crate::{
+    grid::{xorshift, Grid, PinKind},
+    truth_table::{pin_names, settled_outputs, TruthTable},
+};
+
+/// Compares what two grids compute, for example a design and an optimised
+/// version of it, by settling both for the same input vectors and comparing
+/// their output pins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EquivalenceCheck {
+    /// Ticks to settle each grid for, see [`Grid::settle`].
+    pub max_ticks: u64,
+    /// Up to this many inputs, but no more than [`TruthTable::MAX_INPUTS`],
+    /// every input vector is checked, above it `random_vectors` random ones.
+    pub exhaustive_inputs: usize,
+    pub random_vectors: u64,
+    pub seed: u64,
+}
+
+/// How an [`EquivalenceCheck`] ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Equivalence {
+    /// The outputs were the same for all `vectors` checked input vectors,
+    /// which were all there are if `exhaustive` is set.
+    Equal { vectors: u64, exhaustive: bool },
+    /// The first input vector the outputs differ for.
+    Different(Counterexample),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counterexample {
+    /// The input pins and their values.
+    pub inputs: Vec<(String, bool)>,
+    /// The outputs that differ.
+    pub outputs: Vec<Mismatch>,
+}
+
+/// An output pin and its value in both grids, `None` if it never settles.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub name: String,
+    pub a: Option<bool>,
+    pub b: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EquivalenceError {
+    /// The names of the input pins only the first and only the second grid
+    /// has.
+    DifferentInputs {
+        only_a: Vec<String>,
+        only_b: Vec<String>,
+    },
+    /// Like [`EquivalenceError::DifferentInputs`] for the output pins.
+    DifferentOutputs {
+        only_a: Vec<String>,
+        only_b: Vec<String>,
+    },
+}
+
+impl Default for EquivalenceCheck {
+    fn default() -> Self {
+        Self {
+            max_ticks: 10000,
+            exhaustive_inputs: 16,
+            random_vectors: 10000,
+            seed: 1,
+        }
+    }
+}
+
+impl EquivalenceCheck {
+    /// Checks the input vectors in order until the outputs of the grids
+    /// differ. Both start every vector from the state they are in. An output
+    /// that never settles in both grids counts as the same.
+    pub fn run(&self, a: &Grid, b: &Grid) -> Result<Equivalence, EquivalenceError> {
+        let inputs = pin_names(a, PinKind::In);
+        let outputs = pin_names(a, PinKind::Out);
+        let differences = |kind: PinKind, names: &[&str]| {
+            let other = pin_names(b, kind);
+            let only = |names: &[&str], other: &[&str]| -> Vec<String> {
+                (names.iter())
+                    .filter(|name| !other.contains(name))
+                    .map(|name| name.to_string())
+                    .collect()
+            };
+            let (only_a, only_b) = (only(names, &other), only(&other, names));
+            (!only_a.is_empty() || !only_b.is_empty()).then_some((only_a, only_b))
+        };
+        if let Some((only_a, only_b)) = differences(PinKind::In, &inputs) {
+            return Err(EquivalenceError::DifferentInputs { only_a, only_b });
+        }
+        if let Some((only_a, only_b)) = differences(PinKind::Out, &outputs) {
+            return Err(EquivalenceError::DifferentOutputs { only_a, only_b });
+        }
+
+        let exhaustive = inputs.len() <= self.exhaustive_inputs.min(TruthTable::MAX_INPUTS);
+        let vectors = match exhaustive {
+            true => 1 << inputs.len(),
+            false => self.random_vectors,
+        };
+        let mut next = xorshift(self.seed);
+        for vector in 0..vectors {
+            // like the rows of a truth table the first input is the highest bit
+            let values: Vec<bool> = match exhaustive {
+                true => (0..inputs.len())
+                    .map(|i| vector >> (inputs.len() - 1 - i) & 1 == 1)
+                    .collect(),
+                false => (0..inputs.len()).map(|_| next() & 1 == 1).collect(),
+            };
+            let a_outputs = settled_outputs(a, &inputs, &values, &outputs, self.max_ticks);
+            let b_outputs = settled_outputs(b, &inputs, &values, &outputs, self.max_ticks);
+            let mismatches: Vec<Mismatch> =
+                (outputs.iter().zip(a_outputs.into_iter().zip(b_outputs)))
+                    .filter(|(_, (a, b))| a != b)
+                    .map(|(name, (a, b))| Mismatch {
+                        name: name.to_string(),
+                        a,
+                        b,
+                    })
+                    .collect();
+            if !mismatches.is_empty() {
+                return Ok(Equivalence::Different(Counterexample {
+                    inputs: (inputs.iter().zip(values))
+                        .map(|(name, active)| (name.to_string(), active))
+                        .collect(),
+                    outputs: mismatches,
+                }));
+            }
+        }
+        Ok(Equivalence::Equal {
+            vectors,
+            exhaustive,
+        })
+    }
+}
+
+#[test]
+fn equivalence_counterexample() {
+    use crate::grid::{Cell, Direction};
+    let not = Cell::Not {
+        active: false,
+        direction: Direction::Right,
+    };
+    let cable = Cell::Cable {
+        active: false,
+        direction: Direction::Right,
+    };
+    // y is a through the gates and z is b through cables
+    let circuit = |gates: &[Cell]| {
+        let mut grid = Grid::new(gates.len() + 2, 2);
+        for (y, (input, output)) in [("a", "y"), ("b", "z")].into_iter().enumerate() {
+            let input = grid.pin(input, PinKind::In, Direction::Right).unwrap();
+            let output = grid.pin(output, PinKind::Out, Direction::Right).unwrap();
+            grid.place(0, y, input);
+            for (x, gate) in gates.iter().enumerate() {
+                grid.place(x + 1, y, if y == 0 { *gate } else { cable });
+            }
+            grid.place(gates.len() + 1, y, output);
+        }
+        grid
+    };
+    let small = circuit(&[not]);
+    let large = circuit(&[not, not, not]);
+    let check = EquivalenceCheck::default();
+    assert_eq!(
+        check.run(&small, &large),
+        Ok(Equivalence::Equal {
+            vectors: 4,
+            exhaustive: true
+        })
+    );
+    let random = EquivalenceCheck {
+        exhaustive_inputs: 1,
+        random_vectors: 20,
+        ..check.clone()
+    };
+    assert_eq!(
+        random.run(&small, &large),
+        Ok(Equivalence::Equal {
+            vectors: 20,
+            exhaustive: false
+        })
+    );
+
+    let wrong = circuit(&[cable]);
+    let Ok(Equivalence::Different(counterexample)) = check.run(&small, &wrong) else {
+        panic!("a cable isn't a not gate");
+    };
+    assert_eq!(
+        counterexample.inputs,
+        [(String::from("a"), false), (String::from("b"), false)]
+    );
+    assert_eq!(
+        counterexample.outputs,
+        [Mismatch {
+            name: String::from("y"),
+            a: Some(true),
+            b: Some(false),
+        }]
+    );
+
+    let mut extra = large.clone();
+    let pin = extra.pin("c", PinKind::In, Direction::Right).unwrap();
+    extra.place(1, 0, pin);
+    assert_eq!(
+        check.run(&small, &extra),
+        Err(EquivalenceError::DifferentInputs {
+            only_a: vec![],
+            only_b: vec![String::from("c")],
+        })
+    );
+}
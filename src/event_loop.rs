@@ -112,6 +112,7 @@ pub enum Event {
     MouseMove(i32, i32),
     MouseDown(MouseButton),
     MouseUp(MouseButton),
+    #[allow(dead_code)]
     MouseWheel(f64, f64),
     KeyDown(Key),
     KeyUp(Key),
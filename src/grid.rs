@@ -1,4 +1,12 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use crate::{
+    chunks::{Chunk, Chunks, CHUNK_SIZE},
+    netlist::{Netlist, Node},
+    nets::Nets,
+    signal::Signal,
+};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Direction {
     Up,
     Down,
@@ -49,9 +57,9 @@ impl Direction {
     }
 }
 
-impl Into<(i32, i32)> for Direction {
-    fn into(self) -> (i32, i32) {
-        match self {
+impl From<Direction> for (i32, i32) {
+    fn from(direction: Direction) -> Self {
+        match direction {
             Direction::Up => (0, -1),
             Direction::Down => (0, 1),
             Direction::Left => (-1, 0),
@@ -60,17 +68,291 @@ impl Into<(i32, i32)> for Direction {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Cell {
     Empty,
-    Point { active: bool, marked: u32 },
-    Cable { active: bool, direction: Direction },
-    And { active: bool, direction: Direction },
-    Not { active: bool, direction: Direction },
-    Tee { active: bool, direction: Direction },
+    Point {
+        active: bool,
+    },
+    Cable {
+        active: bool,
+        direction: Direction,
+    },
+    And {
+        active: bool,
+        direction: Direction,
+    },
+    Not {
+        active: bool,
+        direction: Direction,
+    },
+    Tee {
+        active: bool,
+        direction: Direction,
+    },
+    Or {
+        active: bool,
+        direction: Direction,
+    },
+    Xor {
+        active: bool,
+        direction: Direction,
+    },
+    Nand {
+        active: bool,
+        direction: Direction,
+    },
+    Nor {
+        active: bool,
+        direction: Direction,
+    },
+    /// Two independent signals crossing each other, the vertical one flows
+    /// in `direction` and the horizontal one to the right of it.
+    Cross {
+        vertical: bool,
+        horizontal: bool,
+        direction: Direction,
+    },
+    /// A cable bending by a quarter turn, it reads from the left of
+    /// `direction`, or from the right when `mirrored`.
+    Corner {
+        active: bool,
+        direction: Direction,
+        mirrored: bool,
+    },
+    /// Outputs its input from `ticks` ticks ago. `stages` is a shift register
+    /// holding the inputs of the last `ticks` ticks, the oldest in bit 0.
+    Delay {
+        direction: Direction,
+        ticks: u8,
+        stages: u32,
+    },
+    /// Stores the signal from the left of `direction` whenever the signal from
+    /// the right rises and outputs it in `direction`. `clock` is the clock
+    /// signal of the last tick.
+    DFlipFlop {
+        active: bool,
+        direction: Direction,
+        clock: bool,
+    },
+    /// Becomes active when the signal from the left of `direction` is, and
+    /// inactive when the one from the right is. Setting wins over resetting.
+    SrLatch {
+        active: bool,
+        direction: Direction,
+    },
+    /// Sends a periodic signal in `direction`, see [`Cell::clock`].
+    Clock {
+        active: bool,
+        direction: Direction,
+        period: u8,
+        phase: u8,
+        high: u8,
+    },
+    /// Keeps its state until it is toggled, sends it in `direction`.
+    Switch {
+        active: bool,
+        direction: Direction,
+    },
+    /// Like a [`Cell::Switch`], the editor only keeps it active while the
+    /// mouse is held down on it.
+    Button {
+        active: bool,
+        direction: Direction,
+    },
+    /// Lights up when any of its neighbours sends a signal into it.
+    Lamp {
+        active: bool,
+    },
+    /// One cell of a display showing a digit, see [`DisplayKind`]. `x` and
+    /// `y` are the position of the cell inside the display, the cells of the
+    /// left column read the inputs from their left, top to bottom.
+    Display {
+        active: bool,
+        kind: DisplayKind,
+        x: u8,
+        y: u8,
+    },
+    /// A named input or output of the circuit, see [`PinKind`]. `name` is
+    /// the index of the name in the pin names of the grid, see [`Grid::pin`].
+    Pin {
+        active: bool,
+        direction: Direction,
+        kind: PinKind,
+        name: u16,
+    },
+    /// One cell of a ROM or RAM block, see [`Memory`]. `memory` is the index
+    /// of the contents in the memories of the grid, see
+    /// [`Grid::place_memory`]. `x` and `y` are the position of the cell
+    /// inside the block.
+    Memory {
+        active: bool,
+        kind: MemoryKind,
+        memory: u16,
+        x: u8,
+        y: u8,
+    },
+}
+
+/// Whether a [`Cell::Pin`] is an input or an output of the circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PinKind {
+    /// Keeps the state set with [`Grid::set_input`] like a [`Cell::Switch`]
+    /// and sends it in its direction.
+    In,
+    /// Passes the signal from behind on like a [`Cell::Cable`], it can be read
+    /// with [`Grid::output`].
+    Out,
+}
+
+/// Whether a [`Memory`] can be written while simulating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemoryKind {
+    Rom,
+    Ram,
+}
+
+/// The contents of a block of [`Cell::Memory`]s.
+///
+/// The cells of the left column latch the inputs from their left, from top
+/// to bottom the address, lowest bit first, and for a RAM the data to write,
+/// write enable and the clock. After every tick the word at the latched
+/// address is sent to the right from the right column, lowest bit at the top.
+/// A RAM stores the data when the clock rises while write enable is active.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Memory {
+    address_bits: u8,
+    data_bits: u8,
+    words: Vec<u8>,
+    /// The clock input of the last tick.
+    clock: bool,
+}
+
+impl Memory {
+    pub const MAX_ADDRESS_BITS: u8 = 8;
+    pub const MAX_DATA_BITS: u8 = 8;
+
+    /// Both widths are clamped to `1..=8` bits. The words are cut or padded
+    /// with zeros to fill the address space.
+    pub fn new(address_bits: u8, data_bits: u8, words: &[u8]) -> Self {
+        let address_bits = address_bits.clamp(1, Self::MAX_ADDRESS_BITS);
+        let data_bits = data_bits.clamp(1, Self::MAX_DATA_BITS);
+        let mask = (u16::MAX >> (16 - data_bits)) as u8;
+        let mut words: Vec<u8> = words.iter().map(|word| word & mask).collect();
+        words.resize(1 << address_bits, 0);
+        Self {
+            address_bits,
+            data_bits,
+            words,
+            clock: false,
+        }
+    }
+    /// The smallest memory holding all of the words.
+    pub fn fitting(words: &[u8]) -> Self {
+        let address_bits = usize::BITS - words.len().saturating_sub(1).leading_zeros();
+        let data_bits = u8::BITS - words.iter().fold(0, |a, b| a | b).leading_zeros();
+        Self::new(address_bits as u8, data_bits as u8, words)
+    }
+    /// Parses words written as hexadecimal numbers separated by whitespace or
+    /// commas, like `0f 1a 3`.
+    pub fn parse_hex(text: &str) -> Option<Vec<u8>> {
+        text.split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|word| !word.is_empty())
+            .map(|word| u8::from_str_radix(word.trim_start_matches("0x"), 16).ok())
+            .collect()
+    }
+    pub fn address_bits(&self) -> u8 {
+        self.address_bits
+    }
+    pub fn data_bits(&self) -> u8 {
+        self.data_bits
+    }
+    /// One word per address.
+    pub fn words(&self) -> &[u8] {
+        &self.words
+    }
+    /// The size of a block of the given kind using this memory.
+    pub fn size(&self, kind: MemoryKind) -> [usize; 2] {
+        let inputs = match kind {
+            MemoryKind::Rom => self.address_bits,
+            MemoryKind::Ram => self.address_bits + self.data_bits + 2,
+        };
+        [2, inputs.max(self.data_bits) as usize]
+    }
+    /// The address from the inputs of a block, one bit per input.
+    pub fn address(&self, inputs: u32) -> usize {
+        inputs as usize & ((1 << self.address_bits) - 1)
+    }
+    /// Writes the data of the inputs if the block is a RAM and the clock
+    /// rises, returns the word at the address.
+    fn step(&mut self, kind: MemoryKind, inputs: u32) -> u8 {
+        let address = self.address(inputs);
+        if kind == MemoryKind::Ram {
+            let mask = u32::MAX >> (32 - self.data_bits);
+            let data = (inputs >> self.address_bits & mask) as u8;
+            let control = inputs >> (self.address_bits + self.data_bits);
+            let clock = control & 0b10 != 0;
+            if control & 0b01 != 0 && clock && !self.clock {
+                self.words[address] = data;
+            }
+            self.clock = clock;
+        }
+        self.words[address]
+    }
+}
+
+/// The two kinds of [`Cell::Display`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DisplayKind {
+    /// Shows its 4 inputs as a hexadecimal digit, the first input is the
+    /// lowest bit.
+    Hex,
+    /// Has one input per segment, from a to g.
+    Segments,
+}
+
+impl DisplayKind {
+    /// Segments a to g of the hexadecimal digits, segment a in the lowest bit.
+    const HEX: [u8; 16] = [
+        0x3F, 0x06, 0x5B, 0x4F, 0x66, 0x6D, 0x7D, 0x07, 0x7F, 0x6F, 0x77, 0x7C, 0x39, 0x5E, 0x79,
+        0x71,
+    ];
+
+    pub fn inputs(self) -> usize {
+        match self {
+            DisplayKind::Hex => 4,
+            DisplayKind::Segments => 7,
+        }
+    }
+    /// Width and height in cells.
+    pub fn size(self) -> [usize; 2] {
+        match self {
+            DisplayKind::Hex => [2, 4],
+            DisplayKind::Segments => [4, 7],
+        }
+    }
+    /// The lit segments given the inputs, one bit per input.
+    pub fn segments(self, inputs: u8) -> u8 {
+        match self {
+            DisplayKind::Hex => Self::HEX[inputs as usize & 0xF],
+            DisplayKind::Segments => inputs & 0x7F,
+        }
+    }
+}
+
+/// Where an output of a cell reads one of its inputs from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    /// The signal the neighbour on this side sends into the cell.
+    Side(Direction),
+    /// Another output of the same cell, as it was before the tick.
+    Output(usize),
 }
 
 impl Cell {
+    /// The longest delay a [`Cell::Delay`] supports.
+    pub const MAX_DELAY: u8 = 32;
+
     pub fn is_active(&self) -> bool {
         match self {
             Cell::Empty => false,
@@ -78,42 +360,329 @@ impl Cell {
             | Cell::And { active, .. }
             | Cell::Not { active, .. }
             | Cell::Tee { active, .. }
+            | Cell::Or { active, .. }
+            | Cell::Xor { active, .. }
+            | Cell::Nand { active, .. }
+            | Cell::Nor { active, .. }
+            | Cell::Corner { active, .. }
+            | Cell::DFlipFlop { active, .. }
+            | Cell::SrLatch { active, .. }
+            | Cell::Clock { active, .. }
+            | Cell::Switch { active, .. }
+            | Cell::Button { active, .. }
+            | Cell::Lamp { active }
+            | Cell::Display { active, .. }
+            | Cell::Pin { active, .. }
+            | Cell::Memory { active, .. }
             | Cell::Point { active, .. } => *active,
+            Cell::Cross {
+                vertical,
+                horizontal,
+                ..
+            } => *vertical || *horizontal,
+            Cell::Delay { stages, .. } => stages & 1 == 1,
         }
     }
     pub fn direction(&self) -> Option<Direction> {
         match self {
-            Self::Empty | Self::Point { .. } => None,
+            Self::Empty
+            | Self::Point { .. }
+            | Self::Lamp { .. }
+            | Self::Display { .. }
+            | Self::Memory { .. } => None,
             Self::Cable { direction, .. }
             | Self::And { direction, .. }
             | Self::Not { direction, .. }
-            | Self::Tee { direction, .. } => Some(*direction),
+            | Self::Tee { direction, .. }
+            | Self::Or { direction, .. }
+            | Self::Xor { direction, .. }
+            | Self::Nand { direction, .. }
+            | Self::Nor { direction, .. }
+            | Self::Cross { direction, .. }
+            | Self::Corner { direction, .. }
+            | Self::Delay { direction, .. }
+            | Self::DFlipFlop { direction, .. }
+            | Self::SrLatch { direction, .. }
+            | Self::Clock { direction, .. }
+            | Self::Switch { direction, .. }
+            | Self::Button { direction, .. }
+            | Self::Pin { direction, .. } => Some(*direction),
         }
     }
     pub fn direction_mut(&mut self) -> Option<&mut Direction> {
         match self {
-            Self::Empty | Self::Point { .. } => None,
+            Self::Empty
+            | Self::Point { .. }
+            | Self::Lamp { .. }
+            | Self::Display { .. }
+            | Self::Memory { .. } => None,
             Self::Cable { direction, .. }
             | Self::And { direction, .. }
             | Self::Not { direction, .. }
-            | Self::Tee { direction, .. } => Some(direction),
+            | Self::Tee { direction, .. }
+            | Self::Or { direction, .. }
+            | Self::Xor { direction, .. }
+            | Self::Nand { direction, .. }
+            | Self::Nor { direction, .. }
+            | Self::Cross { direction, .. }
+            | Self::Corner { direction, .. }
+            | Self::Delay { direction, .. }
+            | Self::DFlipFlop { direction, .. }
+            | Self::SrLatch { direction, .. }
+            | Self::Clock { direction, .. }
+            | Self::Switch { direction, .. }
+            | Self::Button { direction, .. }
+            | Self::Pin { direction, .. } => Some(direction),
         }
     }
-    fn signal_in_direction(&self, dir: Direction) -> bool {
-        self.is_active() && {
-            match self {
-                Cell::Empty => unreachable!(),
-                Cell::Point { .. } => true,
-                Cell::Tee { direction, .. } => {
-                    // is orthogonal
-                    *direction != dir && direction.rev() != dir
+    pub fn signal_in_direction(&self, dir: Direction) -> bool {
+        self.output_towards(dir)
+            .is_some_and(|output| self.output(output))
+    }
+    /// Whether this cell sends a signal out of the given side.
+    pub fn emits(&self, dir: Direction) -> bool {
+        self.output_towards(dir).is_some()
+    }
+    /// Which of the [`Cell::outputs`] leaves this cell through the given side.
+    pub fn output_towards(&self, dir: Direction) -> Option<usize> {
+        match self {
+            Cell::Empty | Cell::Lamp { .. } | Cell::Display { .. } => None,
+            Cell::Point { .. } => Some(0),
+            Cell::Memory { x, .. } => (*x == 1 && dir == Direction::Right).then_some(0),
+            Cell::Tee { direction, .. } => {
+                // is orthogonal
+                (*direction != dir && direction.rev() != dir).then_some(0)
+            }
+            Cell::Cable { direction, .. }
+            | Cell::And { direction, .. }
+            | Cell::Not { direction, .. }
+            | Cell::Or { direction, .. }
+            | Cell::Xor { direction, .. }
+            | Cell::Nand { direction, .. }
+            | Cell::Nor { direction, .. }
+            | Cell::Corner { direction, .. }
+            | Cell::Delay { direction, .. }
+            | Cell::DFlipFlop { direction, .. }
+            | Cell::SrLatch { direction, .. }
+            | Cell::Clock { direction, .. }
+            | Cell::Switch { direction, .. }
+            | Cell::Button { direction, .. }
+            | Cell::Pin { direction, .. } => (*direction == dir).then_some(0),
+            Cell::Cross { direction, .. } => {
+                if *direction == dir {
+                    Some(0)
+                } else if direction.rotate_cw() == dir {
+                    Some(1)
+                } else {
+                    None
                 }
-                Cell::Cable { direction, .. }
-                | Cell::And { direction, .. }
-                | Cell::Not { direction, .. } => *direction == dir,
             }
         }
     }
+    /// Number of independent signals this cell carries, every one of them is
+    /// computed separately by [`Cell::eval`]. Only the
+    /// [`Cell::external_outputs`] leave the cell, the others hold internal
+    /// state.
+    pub fn outputs(&self) -> usize {
+        match self {
+            Cell::Empty => 0,
+            Cell::Cross { .. } | Cell::DFlipFlop { .. } => 2,
+            Cell::Delay { ticks, .. } => *ticks as usize,
+            _ => 1,
+        }
+    }
+    pub fn external_outputs(&self) -> std::ops::Range<usize> {
+        match self {
+            Cell::Empty => 0..0,
+            Cell::Cross { .. } => 0..2,
+            _ => 0..1,
+        }
+    }
+    pub fn output(&self, output: usize) -> bool {
+        match self {
+            Cell::Delay { stages, .. } => stages >> output & 1 == 1,
+            Cell::DFlipFlop { active, clock, .. } => match output {
+                0 => *active,
+                _ => *clock,
+            },
+            Cell::Cross {
+                vertical,
+                horizontal,
+                ..
+            } => match output {
+                0 => *vertical,
+                _ => *horizontal,
+            },
+            _ => self.is_active(),
+        }
+    }
+    pub fn set_output(&mut self, output: usize, signal: bool) {
+        match self {
+            Cell::Delay { stages, .. } => {
+                *stages = *stages & !(1 << output) | (signal as u32) << output
+            }
+            Cell::DFlipFlop { active, clock, .. } => match output {
+                0 => *active = signal,
+                _ => *clock = signal,
+            },
+            Cell::Cross {
+                vertical,
+                horizontal,
+                ..
+            } => match output {
+                0 => *vertical = signal,
+                _ => *horizontal = signal,
+            },
+            _ => self.set(signal),
+        }
+    }
+    /// Whether this cell computes its next state from its inputs on every tick.
+    /// [`Cell::Point`]s are not gates, they are merged into nets instead.
+    pub fn is_gate(&self) -> bool {
+        !matches!(self, Cell::Empty | Cell::Point { .. })
+    }
+    /// Where an output of this cell reads its inputs from, in the order
+    /// [`Cell::eval`] expects them.
+    pub fn inputs(&self, output: usize) -> impl Iterator<Item = Input> {
+        use Input::{Output, Side};
+        let mut inputs = [None; 4];
+        let mut set = |list: &[Input]| {
+            for (input, source) in inputs.iter_mut().zip(list) {
+                *input = Some(*source);
+            }
+        };
+        match *self {
+            Cell::Empty | Cell::Point { .. } | Cell::Clock { .. } => {}
+            Cell::Cable { direction, .. }
+            | Cell::Not { direction, .. }
+            | Cell::Tee { direction, .. } => set(&[Side(direction.rev())]),
+            Cell::And { direction, .. }
+            | Cell::Or { direction, .. }
+            | Cell::Xor { direction, .. }
+            | Cell::Nand { direction, .. }
+            | Cell::Nor { direction, .. } => {
+                set(&[Side(direction.rotate_cw()), Side(direction.rotate_ccw())])
+            }
+            Cell::Cross { direction, .. } => match output {
+                0 => set(&[Side(direction.rev())]),
+                _ => set(&[Side(direction.rotate_ccw())]),
+            },
+            Cell::Corner {
+                direction,
+                mirrored: false,
+                ..
+            } => set(&[Side(direction.rotate_ccw())]),
+            Cell::Corner {
+                direction,
+                mirrored: true,
+                ..
+            } => set(&[Side(direction.rotate_cw())]),
+            Cell::Delay {
+                direction, ticks, ..
+            } => match output + 1 == ticks as usize {
+                true => set(&[Side(direction.rev())]),
+                false => set(&[Output(output + 1)]),
+            },
+            Cell::DFlipFlop { direction, .. } => match output {
+                // data, clock, last clock, stored value
+                0 => set(&[
+                    Side(direction.rotate_ccw()),
+                    Side(direction.rotate_cw()),
+                    Output(1),
+                    Output(0),
+                ]),
+                _ => set(&[Side(direction.rotate_cw())]),
+            },
+            Cell::SrLatch { direction, .. } => set(&[
+                Side(direction.rotate_ccw()),
+                Side(direction.rotate_cw()),
+                Output(0),
+            ]),
+            Cell::Switch { .. }
+            | Cell::Button { .. }
+            | Cell::Pin {
+                kind: PinKind::In, ..
+            } => set(&[Output(0)]),
+            Cell::Pin {
+                direction,
+                kind: PinKind::Out,
+                ..
+            } => set(&[Side(direction.rev())]),
+            Cell::Lamp { .. } => set(&Direction::all().map(Side)),
+            Cell::Display { x: 0, .. } | Cell::Memory { x: 0, .. } => set(&[Side(Direction::Left)]),
+            Cell::Display { .. } => {}
+            // set after every tick, see `Grid::step_memories`
+            Cell::Memory { .. } => set(&[Output(0)]),
+        }
+        inputs.into_iter().flatten()
+    }
+    /// The state of a [`Cell::Clock`] at the given tick of the simulation,
+    /// active during the first `high` of every `period` ticks, shifted by
+    /// `phase` ticks. `None` for every other kind of cell.
+    pub fn clock(&self, tick: u64) -> Option<bool> {
+        match *self {
+            Cell::Clock {
+                period,
+                phase,
+                high,
+                ..
+            } => {
+                let period = period.max(1) as u64;
+                Some((tick + phase as u64) % period < high as u64)
+            }
+            _ => None,
+        }
+    }
+    /// Changes the timing of a [`Cell::Clock`]. The period is at least one
+    /// tick, phase and high time are limited to the period.
+    pub fn set_clock(&mut self, period: u8, phase: u8, high: u8) {
+        if let Cell::Clock {
+            period: p,
+            phase: ph,
+            high: h,
+            ..
+        } = self
+        {
+            *p = period.max(1);
+            *ph = phase % *p;
+            *h = high.min(*p);
+        }
+    }
+    /// The next state of an output given the signals on its [`Cell::inputs`].
+    pub fn eval<S: Signal>(&self, output: usize, inputs: &[S]) -> S {
+        match self {
+            Cell::Empty | Cell::Point { .. } => S::LOW,
+            Cell::Cable { .. }
+            | Cell::Tee { .. }
+            | Cell::Cross { .. }
+            | Cell::Corner { .. }
+            | Cell::Delay { .. }
+            | Cell::Switch { .. }
+            | Cell::Button { .. }
+            | Cell::Pin { .. }
+            | Cell::Memory { .. } => inputs[0],
+            Cell::Not { .. } => !inputs[0],
+            Cell::And { .. } => inputs.iter().fold(S::HIGH, |a, b| a & *b),
+            Cell::Or { .. } | Cell::Lamp { .. } | Cell::Display { .. } => {
+                inputs.iter().fold(S::LOW, |a, b| a | *b)
+            }
+            Cell::Xor { .. } => inputs.iter().fold(S::LOW, |a, b| a ^ *b),
+            Cell::Nand { .. } => !inputs.iter().fold(S::HIGH, |a, b| a & *b),
+            Cell::Nor { .. } => !inputs.iter().fold(S::LOW, |a, b| a | *b),
+            Cell::DFlipFlop { .. } => match output {
+                0 => {
+                    let rising = inputs[1] & !inputs[2];
+                    (rising & inputs[0]) | (!rising & inputs[3])
+                }
+                _ => inputs[0],
+            },
+            Cell::SrLatch { .. } => inputs[0] | (inputs[2] & !inputs[1]),
+            // driven by the tick counter instead, see `Cell::clock`
+            Cell::Clock { active, .. } => S::splat(*active),
+        }
+    }
+    /// Sets the [`Cell::external_outputs`], internal state is kept. A delay
+    /// has every stage set, so it keeps the signal until it changes.
     pub fn set(&mut self, signal: bool) {
         match self {
             Cell::Empty => {}
@@ -121,20 +690,170 @@ impl Cell {
             | Cell::And { active, .. }
             | Cell::Not { active, .. }
             | Cell::Tee { active, .. }
+            | Cell::Or { active, .. }
+            | Cell::Xor { active, .. }
+            | Cell::Nand { active, .. }
+            | Cell::Nor { active, .. }
+            | Cell::Corner { active, .. }
+            | Cell::DFlipFlop { active, .. }
+            | Cell::SrLatch { active, .. }
+            | Cell::Clock { active, .. }
+            | Cell::Switch { active, .. }
+            | Cell::Button { active, .. }
+            | Cell::Lamp { active }
+            | Cell::Display { active, .. }
+            | Cell::Pin { active, .. }
+            | Cell::Memory { active, .. }
             | Cell::Point { active, .. } => *active = signal,
+            Cell::Cross {
+                vertical,
+                horizontal,
+                ..
+            } => {
+                *vertical = signal;
+                *horizontal = signal;
+            }
+            Cell::Delay { ticks, stages, .. } => {
+                *stages = match signal {
+                    true => u32::MAX >> (32 - *ticks as u32),
+                    false => 0,
+                }
+            }
         }
     }
     pub fn rotate(&mut self) {
         match self {
-            Cell::Empty | Cell::Point { .. } => {}
+            Cell::Empty
+            | Cell::Point { .. }
+            | Cell::Lamp { .. }
+            | Cell::Display { .. }
+            | Cell::Memory { .. } => {}
             Cell::Cable { direction, .. }
             | Cell::And { direction, .. }
             | Cell::Not { direction, .. }
-            | Cell::Tee { direction, .. } => *direction = direction.rotate_cw(),
+            | Cell::Tee { direction, .. }
+            | Cell::Or { direction, .. }
+            | Cell::Xor { direction, .. }
+            | Cell::Nand { direction, .. }
+            | Cell::Nor { direction, .. }
+            | Cell::Cross { direction, .. }
+            | Cell::Corner { direction, .. }
+            | Cell::Delay { direction, .. }
+            | Cell::DFlipFlop { direction, .. }
+            | Cell::SrLatch { direction, .. }
+            | Cell::Clock { direction, .. }
+            | Cell::Switch { direction, .. }
+            | Cell::Button { direction, .. }
+            | Cell::Pin { direction, .. } => *direction = direction.rotate_cw(),
+        }
+    }
+    /// Changes the delay of a [`Cell::Delay`], clamped to
+    /// `1..=`[`Cell::MAX_DELAY`]. Keeps the most recent inputs.
+    pub fn set_delay(&mut self, delay: u8) {
+        if let Cell::Delay { ticks, stages, .. } = self {
+            let delay = delay.clamp(1, Cell::MAX_DELAY);
+            let shifted = match delay > *ticks {
+                true => *stages << (delay - *ticks),
+                false => *stages >> (*ticks - delay),
+            };
+            *stages = shifted & u32::MAX >> (32 - delay as u32);
+            *ticks = delay;
+        }
+    }
+    /// Flips the cells which come in a mirrored variant.
+    pub fn mirror(&mut self) {
+        if let Cell::Corner { mirrored, .. } = self {
+            *mirrored = !*mirrored;
         }
     }
 }
 
+impl Cell {
+    /// State saved after the first byte of the cell, for the kinds whose
+    /// state doesn't fit into it.
+    pub fn extra_bytes(&self) -> Vec<u8> {
+        match self {
+            Cell::Cross { horizontal, .. } => vec![*horizontal as u8],
+            Cell::Corner { mirrored, .. } => vec![*mirrored as u8],
+            Cell::DFlipFlop { clock, .. } => vec![*clock as u8],
+            Cell::Display { kind, x, y, .. } => vec![*kind as u8, *x, *y],
+            Cell::Memory {
+                kind, memory, x, y, ..
+            } => {
+                let [low, high] = memory.to_le_bytes();
+                vec![*kind as u8, low, high, *x, *y]
+            }
+            Cell::Pin { kind, name, .. } => {
+                let [low, high] = name.to_le_bytes();
+                vec![*kind as u8, low, high]
+            }
+            Cell::Clock {
+                period,
+                phase,
+                high,
+                ..
+            } => vec![*period, *phase, *high],
+            Cell::Delay { ticks, stages, .. } => std::iter::once(*ticks)
+                .chain(stages.to_le_bytes())
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+    /// Reads the [`Cell::extra_bytes`] into a cell parsed from its first byte.
+    pub fn read_extra_bytes(
+        &mut self,
+        bytes: &mut (impl Iterator<Item = u8> + ?Sized),
+    ) -> Result<(), CellParseError> {
+        let first = u8::from(*self);
+        let mut next = || bytes.next().ok_or(CellParseError::InputTooShort(first));
+        match self {
+            Cell::Cross { horizontal, .. } => *horizontal = next()? & 1 == 1,
+            Cell::Corner { mirrored, .. } => *mirrored = next()? & 1 == 1,
+            Cell::DFlipFlop { clock, .. } => *clock = next()? & 1 == 1,
+            Cell::Display { kind, x, y, .. } => {
+                *kind = match next()? {
+                    0 => DisplayKind::Hex,
+                    _ => DisplayKind::Segments,
+                };
+                *x = next()?;
+                *y = next()?;
+            }
+            Cell::Pin { kind, name, .. } => {
+                *kind = match next()? {
+                    0 => PinKind::In,
+                    _ => PinKind::Out,
+                };
+                *name = u16::from_le_bytes([next()?, next()?]);
+            }
+            Cell::Memory {
+                kind, memory, x, y, ..
+            } => {
+                *kind = match next()? {
+                    0 => MemoryKind::Rom,
+                    _ => MemoryKind::Ram,
+                };
+                *memory = u16::from_le_bytes([next()?, next()?]);
+                *x = next()?;
+                *y = next()?;
+            }
+            Cell::Clock { .. } => {
+                let (period, phase, high) = (next()?, next()?, next()?);
+                self.set_clock(period, phase, high);
+            }
+            Cell::Delay { ticks, stages, .. } => {
+                *ticks = next()?.clamp(1, Cell::MAX_DELAY);
+                let bytes = [next()?, next()?, next()?, next()?];
+                *stages = u32::from_le_bytes(bytes) & u32::MAX >> (32 - *ticks as u32);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// A cell is saved as a single byte, the kind in the upper five bits followed
+/// by two bits for the direction and one for whether its first output is
+/// active. Everything else is stored in [`Cell::extra_bytes`].
 impl From<Cell> for u8 {
     fn from(cell: Cell) -> Self {
         let kind: u8 = match cell {
@@ -144,6 +863,22 @@ impl From<Cell> for u8 {
             Cell::Not { .. } => 3,
             Cell::Tee { .. } => 4,
             Cell::Point { .. } => 5,
+            Cell::Or { .. } => 6,
+            Cell::Xor { .. } => 7,
+            Cell::Nand { .. } => 8,
+            Cell::Nor { .. } => 9,
+            Cell::Cross { .. } => 10,
+            Cell::Corner { .. } => 11,
+            Cell::Delay { .. } => 12,
+            Cell::DFlipFlop { .. } => 13,
+            Cell::SrLatch { .. } => 14,
+            Cell::Clock { .. } => 15,
+            Cell::Switch { .. } => 16,
+            Cell::Button { .. } => 17,
+            Cell::Lamp { .. } => 18,
+            Cell::Display { .. } => 19,
+            Cell::Pin { .. } => 20,
+            Cell::Memory { .. } => 21,
         };
         let dir: u8 = match cell.direction().unwrap_or(Direction::Up) {
             Direction::Up => 0,
@@ -151,7 +886,7 @@ impl From<Cell> for u8 {
             Direction::Down => 2,
             Direction::Left => 3,
         };
-        let active = cell.is_active() as u8;
+        let active = cell.output(0) as u8;
         kind << 3 | dir << 1 | active
     }
 }
@@ -160,16 +895,17 @@ impl From<Cell> for u8 {
 pub enum CellParseError {
     DirectionInvalid(u8, u8),
     KindInvalid(u8, u8),
+    InputTooShort(u8),
 }
 
 impl TryFrom<u8> for Cell {
     type Error = CellParseError;
 
     fn try_from(value: u8) -> Result<Self, Self::Error> {
-        let kind = (value & 0b111000) >> 3;
+        let kind = value >> 3;
         let active = (value & 0b1) == 1;
         if kind == 5 {
-            return Ok(Cell::Point { active, marked: 0 });
+            return Ok(Cell::Point { active });
         }
         let direction = (value & 0b110) >> 1;
         let direction = match direction {
@@ -186,35 +922,193 @@ impl TryFrom<u8> for Cell {
             3 => Cell::Not { active, direction },
             4 => Cell::Tee { active, direction },
             5 => unreachable!(),
+            6 => Cell::Or { active, direction },
+            7 => Cell::Xor { active, direction },
+            8 => Cell::Nand { active, direction },
+            9 => Cell::Nor { active, direction },
+            10 => Cell::Cross {
+                vertical: active,
+                horizontal: false,
+                direction,
+            },
+            11 => Cell::Corner {
+                active,
+                direction,
+                mirrored: false,
+            },
+            12 => Cell::Delay {
+                direction,
+                ticks: 1,
+                stages: active as u32,
+            },
+            13 => Cell::DFlipFlop {
+                active,
+                direction,
+                clock: false,
+            },
+            14 => Cell::SrLatch { active, direction },
+            15 => Cell::Clock {
+                active,
+                direction,
+                period: 2,
+                phase: 0,
+                high: 1,
+            },
+            16 => Cell::Switch { active, direction },
+            17 => Cell::Button { active, direction },
+            18 => Cell::Lamp { active },
+            19 => Cell::Display {
+                active,
+                kind: DisplayKind::Hex,
+                x: 0,
+                y: 0,
+            },
+            20 => Cell::Pin {
+                active,
+                direction,
+                kind: PinKind::In,
+                name: 0,
+            },
+            21 => Cell::Memory {
+                active,
+                kind: MemoryKind::Rom,
+                memory: 0,
+                x: 0,
+                y: 0,
+            },
             n => return Err(CellParseError::KindInvalid(value, n)),
         })
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub struct Grid {
     width: usize,
     height: usize,
-    cells: Vec<Cell>,
-    next: Vec<Cell>,
-    pub marker: u32,
+    cells: Chunks,
+    nets: Option<Nets>,
+    netlist: Option<Netlist>,
+    /// Number of ticks simulated so far, drives the [`Cell::Clock`]s.
+    tick: u64,
+    /// Names of the [`Cell::Pin`]s, indexed by their `name`.
+    pin_names: Vec<String>,
+    /// Circuits that can be placed as a whole, see [`Grid::define_module`].
+    modules: Vec<Module>,
+    instances: Vec<Instance>,
+    /// Contents of the [`Cell::Memory`] blocks, indexed by their `memory`.
+    memories: Vec<Memory>,
+    /// Top left corners of the memory blocks.
+    memory_blocks: Option<Vec<[usize; 2]>>,
+    /// Counts the edits, see [`Grid::revision`].
+    revision: u64,
 }
 
+/// A named circuit defined from a region of a grid. Its [`Cell::Pin`]s are
+/// the connections to the outside of its [`Instance`]s.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Module {
+    pub name: String,
+    pub grid: Grid,
+}
+
+/// A copy of a [`Module`] placed into a grid with its top left corner at
+/// `pos`.
+///
+/// The cells of the module are copied into the grid, so instances are
+/// simulated like everything else. Pins become [`Cell::Cable`]s, an input pin
+/// on the border reads the signal from outside and an output pin sends its
+/// signal out. The copied cells can't be edited on their own, only by
+/// changing the module with [`Grid::set_module`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Instance {
+    pub module: usize,
+    pub pos: [usize; 2],
+}
+
+/// How [`Grid::settle`] ended.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Settle {
+    /// The last of the `ticks` simulated ticks didn't change any cell.
+    Stable { ticks: u64 },
+    /// After `ticks` ticks the grid was back in the state it had `period`
+    /// ticks before. `cells` are the cells changing during the cycle, row by
+    /// row.
+    Oscillating {
+        ticks: u64,
+        period: u64,
+        cells: Vec<[usize; 2]>,
+    },
+    /// Neither happened within the tick limit.
+    Unsettled,
+}
+
+/// Which part of the contents keeps its place along one axis when
+/// [`Grid::resize`] changes the size.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Anchor {
+    /// The top or left edge.
+    Start,
+    Middle,
+    /// The bottom or right edge.
+    End,
+}
+
+impl PartialEq for Grid {
+    fn eq(&self, other: &Self) -> bool {
+        self.width == other.width
+            && self.height == other.height
+            && self.filled().eq(other.filled())
+            && self.pin_names == other.pin_names
+            && self.modules == other.modules
+            && self.instances == other.instances
+            && self.memories == other.memories
+    }
+}
+
+impl Eq for Grid {}
+
+/// Goes through the cells of the allocated chunks row by row, the other
+/// cells are all empty.
 pub struct GridIterator<'a> {
-    inner: <&'a Vec<Cell> as IntoIterator>::IntoIter,
-    i: usize,
+    cells: &'a Chunks,
     width: usize,
+    height: usize,
+    /// The row of chunks being iterated and its chunks with their columns.
+    row: usize,
+    chunks: Vec<(usize, &'a Chunk)>,
+    /// Row of cells inside the row of chunks.
+    y: usize,
+    /// Column of cells inside the row of chunks, across all of its chunks.
+    i: usize,
 }
 
 impl<'a> Iterator for GridIterator<'a> {
     type Item = ([usize; 2], &'a Cell);
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next().map(|cell| {
-            let res = ([self.i % self.width, self.i / self.width], cell);
-            self.i += 1;
-            res
-        })
+        loop {
+            if let Some(&(column, chunk)) = self.chunks.get(self.i / CHUNK_SIZE) {
+                let x = self.i % CHUNK_SIZE;
+                self.i += 1;
+                let pos = [column * CHUNK_SIZE + x, self.row * CHUNK_SIZE + self.y];
+                // chunks at the edges reach past the grid
+                if pos[0] < self.width && pos[1] < self.height {
+                    return Some((pos, &chunk[x + self.y * CHUNK_SIZE]));
+                }
+                continue;
+            }
+            self.i = 0;
+            if !self.chunks.is_empty() && self.y + 1 < CHUNK_SIZE {
+                self.y += 1;
+                continue;
+            }
+            let from = match self.chunks.is_empty() {
+                true => self.row,
+                false => self.row + 1,
+            };
+            (self.row, self.chunks) = self.cells.row(from)?;
+            self.y = 0;
+        }
     }
 }
 
@@ -224,206 +1118,1194 @@ impl<'a> IntoIterator for &'a Grid {
 
     fn into_iter(self) -> Self::IntoIter {
         GridIterator {
+            cells: &self.cells,
             width: self.width,
-            inner: (&self.cells).into_iter(),
+            height: self.height,
+            row: 0,
+            chunks: Vec::new(),
+            y: 0,
             i: 0,
         }
     }
 }
 
 impl Grid {
+    /// Width and height of [`Grid::unbounded`] grids.
+    pub const UNBOUNDED: usize = 1 << 30;
+
+    /// An empty grid, memory is only used for the parts that aren't empty.
     pub fn new(width: usize, height: usize) -> Self {
-        let cells: Vec<Cell> = (0..width * height).map(|_| Cell::Empty).collect();
         Self {
             width,
-            next: cells.clone(),
-            cells,
+            cells: Chunks::default(),
             height,
-            marker: 1,
+            nets: None,
+            netlist: None,
+            tick: 0,
+            pin_names: Vec::new(),
+            modules: Vec::new(),
+            instances: Vec::new(),
+            memories: Vec::new(),
+            memory_blocks: None,
+            revision: 0,
         }
     }
-    fn mark(
-        &mut self,
-        marker: u32,
-        x: i32,
-        y: i32,
-        f: &mut Option<&mut impl FnMut(&mut Cell)>,
-    ) -> bool {
-        match &mut self[[x, y]] {
-            Cell::Point { marked, .. } => {
-                if *marked == marker {
-                    return false;
-                }
-                *marked = marker;
-                if x > 0 {
-                    self.mark(marker, x - 1, y, f);
-                }
-                if x + 1 < self.width as i32 {
-                    self.mark(marker, x + 1, y, f);
-                }
-                if y > 0 {
-                    self.mark(marker, x, y - 1, f);
+    /// A grid reaching [`Grid::UNBOUNDED`] cells to the right and down, which
+    /// is as good as endless.
+    pub fn unbounded() -> Self {
+        Self::new(Self::UNBOUNDED, Self::UNBOUNDED)
+    }
+    pub fn is_unbounded(&self) -> bool {
+        self.width == Self::UNBOUNDED && self.height == Self::UNBOUNDED
+    }
+    /// The size of the smallest region at the top left corner of the grid
+    /// containing all non-empty cells.
+    pub fn extent(&self) -> [usize; 2] {
+        self.filled().fold([0, 0], |[width, height], ([x, y], _)| {
+            [width.max(x + 1), height.max(y + 1)]
+        })
+    }
+    /// The top left corner and size of the smallest region containing all
+    /// non-empty cells, `None` if the grid is empty.
+    pub fn bounding_box(&self) -> Option<([usize; 2], [usize; 2])> {
+        let [width, height] = self.extent();
+        let left = self.filled().map(|([x, _], _)| x).min()?;
+        let top = self.filled().next()?.0[1];
+        Some(([left, top], [width - left, height - top]))
+    }
+    /// Moves every cell by `offset` into a grid of the given size. Whatever
+    /// doesn't fit anymore is erased, together with the rest of the instance
+    /// or block it belongs to.
+    fn reframe(&mut self, offset: [i64; 2], [width, height]: [usize; 2]) {
+        let moved = |[x, y]: [usize; 2]| {
+            let (x, y) = (x as i64 + offset[0], y as i64 + offset[1]);
+            (x >= 0 && y >= 0 && x < width as i64 && y < height as i64)
+                .then_some([x as usize, y as usize])
+        };
+        for instance in (0..self.instances.len()).rev() {
+            let Instance { module, pos } = self.instances[instance];
+            let [w, h] = self.module_size(module);
+            let end = [pos[0] + w - 1, pos[1] + h - 1];
+            if moved(pos).is_none() || moved(end).is_none() {
+                self.remove_instance(instance);
+            }
+        }
+        let outside: Vec<[usize; 2]> = self
+            .filled()
+            .map(|(pos, _)| pos)
+            .filter(|&pos| moved(pos).is_none())
+            .collect();
+        for [x, y] in outside {
+            self.erase(x, y);
+        }
+        let mut cells = Chunks::default();
+        for (pos, cell) in self.filled() {
+            *cells.get_mut(moved(pos).unwrap()) = *cell;
+        }
+        self.cells = cells;
+        for instance in &mut self.instances {
+            instance.pos = moved(instance.pos).unwrap();
+        }
+        self.width = width;
+        self.height = height;
+        self.edited();
+    }
+    /// Changes the size of the grid, the anchor says for both axes which part
+    /// of the contents stays in place. Returns how far the contents moved.
+    pub fn resize(&mut self, size: [usize; 2], anchor: [Anchor; 2]) -> [i64; 2] {
+        let size = size.map(|len| len.min(Self::UNBOUNDED));
+        let offset = [0, 1].map(|axis| {
+            let old = [self.width, self.height][axis] as i64;
+            match anchor[axis] {
+                Anchor::Start => 0,
+                Anchor::Middle => (size[axis] as i64 - old) / 2,
+                Anchor::End => size[axis] as i64 - old,
+            }
+        });
+        self.reframe(offset, size);
+        offset
+    }
+    /// Adds rows or columns at the given edge, sizes stop growing at
+    /// [`Grid::UNBOUNDED`]. Returns how far the contents moved.
+    pub fn add_lines(&mut self, side: Direction, count: usize) -> [i64; 2] {
+        let grow = |len: usize| (len + count).min(Self::UNBOUNDED);
+        let (width, height) = (self.width, self.height);
+        let (offset, size) = match side {
+            Direction::Up => ([0, count as i64], [width, grow(height)]),
+            Direction::Down => ([0, 0], [width, grow(height)]),
+            Direction::Left => ([count as i64, 0], [grow(width), height]),
+            Direction::Right => ([0, 0], [grow(width), height]),
+        };
+        self.reframe(offset, size);
+        offset
+    }
+    /// Removes rows or columns at the given edge. Returns how far the contents
+    /// moved.
+    pub fn remove_lines(&mut self, side: Direction, count: usize) -> [i64; 2] {
+        let (width, height) = (self.width, self.height);
+        let (offset, size) = match side {
+            Direction::Up => ([0, -(count as i64)], [width, height.saturating_sub(count)]),
+            Direction::Down => ([0, 0], [width, height.saturating_sub(count)]),
+            Direction::Left => ([-(count as i64), 0], [width.saturating_sub(count), height]),
+            Direction::Right => ([0, 0], [width.saturating_sub(count), height]),
+        };
+        self.reframe(offset, size);
+        offset
+    }
+    /// Shrinks the grid to the [`Grid::bounding_box`] of its cells, empty
+    /// grids are left alone. Returns how far the contents moved.
+    pub fn crop(&mut self) -> [i64; 2] {
+        let Some((pos, size)) = self.bounding_box() else {
+            return [0, 0];
+        };
+        let offset = pos.map(|p| -(p as i64));
+        self.reframe(offset, size);
+        offset
+    }
+    /// Moves the contents inside the grid, whatever is moved past the edges is
+    /// erased.
+    pub fn shift(&mut self, offset: [i64; 2]) {
+        self.reframe(offset, [self.width, self.height]);
+    }
+    /// The cells that aren't empty.
+    fn filled(&self) -> impl Iterator<Item = ([usize; 2], &Cell)> {
+        self.into_iter().filter(|(_, cell)| **cell != Cell::Empty)
+    }
+    /// Drops everything derived from the layout of the grid.
+    fn edited(&mut self) {
+        self.nets = None;
+        self.netlist = None;
+        self.memory_blocks = None;
+        self.revision += 1;
+    }
+    /// Changes whenever the layout of the grid is edited, but not when
+    /// signals change by simulating or [`Grid::set_active`].
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+    /// The contents of all memory blocks, indexed by their `memory`.
+    pub fn memories(&self) -> &[Memory] {
+        &self.memories
+    }
+    /// Puts back a state of the cells and memories recorded at the given tick
+    /// without counting as an edit. The cells have to be at the positions of
+    /// non-empty cells.
+    pub(crate) fn restore(&mut self, cells: &[([usize; 2], Cell)], memories: &[Memory], tick: u64) {
+        for &(pos, cell) in cells {
+            *self.cells.get_mut(pos) = cell;
+        }
+        self.memories.clone_from_slice(memories);
+        self.tick = tick;
+        self.netlist = None;
+    }
+    pub fn simulate(&mut self) {
+        self.netlist = None;
+        let nets = self.nets.take().unwrap_or_else(|| {
+            // frees the chunks erased since the last edit
+            self.cells.shrink();
+            Nets::build(self)
+        });
+        for net in nets.iter() {
+            let active = net
+                .drivers
+                .iter()
+                .any(|&(pos, dir)| self.cells.get(pos).signal_in_direction(dir));
+            for &pos in &net.points {
+                self.cells.get_mut(pos).set(active);
+            }
+        }
+        self.nets = Some(nets);
+        let mut next = self.cells.clone();
+        for ([x, y], &cell) in &*self {
+            if !cell.is_gate() {
+                continue;
+            }
+            for output in 0..cell.outputs() {
+                let mut inputs = [false; 4];
+                let mut len = 0;
+                for input in cell.inputs(output) {
+                    inputs[len] = match input {
+                        Input::Side(dir) => self.signal_from(x, y, dir),
+                        Input::Output(output) => cell.output(output),
+                    };
+                    len += 1;
                 }
-                if y + 1 < self.height as i32 {
-                    self.mark(marker, x, y + 1, f);
+                let active = match cell.clock(self.tick + 1) {
+                    Some(active) => active,
+                    None => cell.eval(output, &inputs[..len]),
+                };
+                next.get_mut([x, y]).set_output(output, active);
+            }
+        }
+        self.cells = next;
+        self.tick += 1;
+        self.step_memories();
+    }
+    /// Same as [`Grid::simulate`], but only re-evaluates the cells whose
+    /// inputs changed during the last tick. The grid is compiled into a
+    /// [`Netlist`] on the first call and again after every edit.
+    pub fn simulate_incremental(&mut self) {
+        let mut netlist = self.netlist.take().unwrap_or_else(|| {
+            self.cells.shrink();
+            Netlist::compile(self)
+        });
+        netlist.step_incremental();
+        self.tick = netlist.tick();
+        for node in netlist.changed() {
+            let active = netlist.is_active(node);
+            match node {
+                Node::Gate(g) => {
+                    let gate = &netlist.gates()[g];
+                    let [x, y] = gate.pos;
+                    self.cells.get_mut([x, y]).set_output(gate.output, active);
                 }
-                if let Some(f) = f {
-                    f(&mut self[[x, y]]);
+                Node::Net(n) => {
+                    for &pos in &netlist.nets()[n].points {
+                        self.cells.get_mut(pos).set(active);
+                    }
                 }
-                true
             }
-            _ => false,
         }
+        self.netlist = Some(netlist);
+        self.step_memories();
     }
-    pub fn simulate(self: &mut Box<Self>) {
-        for x in 0..self.width as i32 {
-            for y in 0..self.height as i32 {
-                if self.mark(self.marker, x, y, &mut Some(&mut |c| c.set(false))) {
-                    self.marker += 1;
+    /// Simulates until no cell changes anymore or the grid repeats an earlier
+    /// state, at most `max_ticks` ticks and one more cycle to confirm an
+    /// oscillation.
+    pub fn settle(&mut self, max_ticks: u64) -> Settle {
+        self.settle_with(max_ticks, Grid::simulate_incremental)
+    }
+    /// Like [`Grid::settle`], but advances the grid by one tick with `step`,
+    /// for example to keep some inputs set.
+    pub fn settle_with(&mut self, max_ticks: u64, mut step: impl FnMut(&mut Grid)) -> Settle {
+        // clocks change without their cell changing, so where they are in
+        // their period is part of the state
+        let state = |grid: &Grid| {
+            let clocks: Vec<u64> = grid
+                .into_iter()
+                .filter_map(|(_, cell)| match *cell {
+                    Cell::Clock { period, phase, .. } => {
+                        Some((grid.tick + phase as u64) % period.max(1) as u64)
+                    }
+                    _ => None,
+                })
+                .collect();
+            let cells: Vec<([usize; 2], Cell)> =
+                grid.filled().map(|(pos, cell)| (pos, *cell)).collect();
+            (cells, grid.memories.clone(), clocks)
+        };
+        type State = (Vec<([usize; 2], Cell)>, Vec<Memory>, Vec<u64>);
+        let hash = |state: &State| {
+            use std::hash::{Hash, Hasher};
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            state.hash(&mut hasher);
+            hasher.finish()
+        };
+        let mut previous = state(self);
+        let mut seen = HashMap::from([(hash(&previous), 0)]);
+        let mut ticks = 0;
+        while ticks < max_ticks {
+            step(self);
+            ticks += 1;
+            let current = state(self);
+            if current == previous {
+                return Settle::Stable { ticks };
+            }
+            let Some(&start) = seen.get(&hash(&current)) else {
+                seen.insert(hash(&current), ticks);
+                previous = current;
+                continue;
+            };
+            // run the cycle once more to find the changing cells, which also
+            // rules out hash collisions
+            let period = ticks - start;
+            let mut changed = vec![false; current.0.len()];
+            for _ in 0..period {
+                step(self);
+                for (changed, (pos, first)) in changed.iter_mut().zip(&current.0) {
+                    *changed |= self.cells.get(*pos) != first;
                 }
             }
+            ticks += period;
+            previous = state(self);
+            if previous == current {
+                let cells = (0..changed.len())
+                    .filter(|&i| changed[i])
+                    .map(|i| current.0[i].0)
+                    .collect();
+                return Settle::Oscillating {
+                    ticks,
+                    period,
+                    cells,
+                };
+            }
+            seen.insert(hash(&previous), ticks);
         }
-        for x in 0..self.width as i32 {
-            for y in 0..self.height as i32 {
-                match self[[x, y]] {
-                    Cell::Point { .. } => {}
-                    _ => continue,
+        Settle::Unsettled
+    }
+    /// Reads the inputs latched by the memory blocks during the last tick and
+    /// sets their outputs, see [`Memory`].
+    fn step_memories(&mut self) {
+        if self.memories.is_empty() {
+            return;
+        }
+        let blocks = self.memory_blocks.take().unwrap_or_else(|| {
+            self.into_iter()
+                .filter(|(_, cell)| matches!(cell, Cell::Memory { x: 0, y: 0, .. }))
+                .map(|(pos, _)| pos)
+                .collect()
+        });
+        for &[x, y] in &blocks {
+            let Cell::Memory { kind, memory, .. } = self[[x, y]] else {
+                continue;
+            };
+            let Some(contents) = self.memories.get_mut(memory as usize) else {
+                continue;
+            };
+            let [_, height] = contents.size(kind);
+            let data_bits = contents.data_bits as usize;
+            let inputs = (0..height.min(self.height - y)).fold(0, |inputs, i| {
+                inputs | (self.cells.get([x, y + i]).is_active() as u32) << i
+            });
+            let word = contents.step(kind, inputs);
+            for i in 0..data_bits.min(self.height - y) {
+                let active = word >> i & 1 == 1;
+                let Some(&cell) = self.get(x + 1, y + i) else {
+                    break;
                 };
-                if self[[x, y]].is_active() {
-                    continue;
-                }
-                for dir in Direction::all() {
-                    let (ox, oy): (i32, i32) = dir.into();
-                    let nx = x + ox;
-                    let ny = y + oy;
-                    if nx < 0 || nx >= self.width as i32 || ny < 0 || ny >= self.height as i32 {
-                        continue;
-                    }
-                    if self[[nx, ny]].signal_in_direction(dir.rev()) {
-                        if self.mark(self.marker, x, y, &mut Some(&mut |c| c.set(true))) {
-                            self.marker += 1;
-                        }
-                        break;
-                    }
+                if matches!(cell, Cell::Memory { .. }) && cell.is_active() != active {
+                    self.set_output(x + 1, y + i, 0, active);
                 }
             }
         }
-        self.next.as_mut_slice().copy_from_slice(&self.cells);
-        for x in 0..self.width {
-            for y in 0..self.height {
-                match self.cells[x + y * self.width] {
-                    Cell::Empty | Cell::Point { .. } => {}
-                    Cell::Cable { direction, .. } => {
-                        let active = {
-                            let (ox, oy): (i32, i32) = direction.rev().into();
-                            let x = x as i32 + ox;
-                            let y = y as i32 + oy;
-                            if x < 0 || x >= self.width as i32 || y < 0 || y >= self.height as i32 {
-                                false
-                            } else {
-                                self[[x, y]].signal_in_direction(direction)
-                            }
-                        };
-                        self.next[x + y * self.width].set(active);
-                    }
-                    Cell::And { direction, .. } => {
-                        let active = [direction.rotate_cw(), direction.rotate_ccw()]
-                            .into_iter()
-                            .all(|dir| {
-                                let (ox, oy): (i32, i32) = dir.into();
-                                let x = x as i32 + ox;
-                                let y = y as i32 + oy;
-                                if x < 0
-                                    || x >= self.width as i32
-                                    || y < 0
-                                    || y >= self.height as i32
-                                {
-                                    return false;
-                                }
-                                let neighbour = &self[[x, y]];
-                                neighbour.signal_in_direction(dir.rev())
-                            });
-                        self.next[x + y * self.width].set(active);
-                    }
-                    Cell::Not { direction, .. } => {
-                        let (ox, oy): (i32, i32) = direction.rev().into();
-                        let nx = x as i32 + ox;
-                        let ny = y as i32 + oy;
-                        let signal = nx >= 0
-                            && nx < self.width as i32
-                            && ny >= 0
-                            && ny < self.height as i32
-                            && self[[nx, ny]].signal_in_direction(direction);
-                        self.next[x + y * self.width].set(!signal)
-                    }
-                    Cell::Tee { direction, .. } => {
-                        let (ox, oy): (i32, i32) = direction.rev().into();
-                        let nx = x as i32 + ox;
-                        let ny = y as i32 + oy;
-                        let signal = nx >= 0
-                            && nx < self.width as i32
-                            && ny >= 0
-                            && ny < self.height as i32
-                            && self[[nx, ny]].signal_in_direction(direction);
-                        self.next[x + y * self.width].set(signal)
-                    }
+        self.memory_blocks = Some(blocks);
+    }
+    /// Whether the neighbour on the given side of a cell sends a signal into it.
+    fn signal_from(&self, x: usize, y: usize, dir: Direction) -> bool {
+        let (ox, oy): (i32, i32) = dir.into();
+        let nx = x as i32 + ox;
+        let ny = y as i32 + oy;
+        nx >= 0
+            && nx < self.width as i32
+            && ny >= 0
+            && ny < self.height as i32
+            && self[[nx, ny]].signal_in_direction(dir.rev())
+    }
+    /// Changes the state of a cell without touching its kind, unlike the
+    /// other ways of mutating the grid this keeps the net table.
+    pub fn set_active(&mut self, x: usize, y: usize, active: bool) {
+        if let Some(cell) = self.get(x, y) {
+            for output in cell.external_outputs() {
+                self.set_output(x, y, output, active);
+            }
+        }
+    }
+    /// Like [`Grid::set_active`], but only changes one of the
+    /// [`Cell::outputs`] of a cell.
+    pub fn set_output(&mut self, x: usize, y: usize, output: usize, active: bool) {
+        if x < self.width && y < self.height {
+            if let Some(cell) = self.cells.get_mut_allocated([x, y]) {
+                cell.set_output(output, active);
+            }
+            if let Some(netlist) = &mut self.netlist {
+                let node = match netlist.node(x, y) {
+                    Some(Node::Gate(g)) => Some(Node::Gate(g + output)),
+                    node => node,
+                };
+                if let Some(node) = node {
+                    netlist.set_active(node, active);
                 }
             }
         }
-        std::mem::swap(&mut self.cells, &mut self.next)
     }
     pub fn width(&self) -> usize {
         self.width
     }
+    /// Number of ticks simulated since the grid was created or loaded.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
     pub fn height(&self) -> usize {
         self.height
     }
+    /// Positions of all [`Cell::Switch`]es and [`Cell::Button`]s, row by row.
+    /// They keep the state set with [`Grid::set_active`] while simulating,
+    /// which makes them the inputs of a circuit.
+    pub fn switches(&self) -> impl Iterator<Item = [usize; 2]> + '_ {
+        self.into_iter()
+            .filter(|(_, cell)| matches!(cell, Cell::Switch { .. } | Cell::Button { .. }))
+            .map(|(pos, _)| pos)
+    }
+    /// Puts a cell into the grid. A [`Cell::Display`] or [`Cell::Memory`] is
+    /// put down as a whole with its top left corner at the given position,
+    /// nothing happens if it doesn't fit. Blocks partly covered by the new
+    /// cells are removed. Placing a cell of a memory block in the grid gives
+    /// the new block a copy of the contents, see [`Grid::place_memory`].
+    pub fn place(&mut self, x: usize, y: usize, cell: Cell) {
+        if let Cell::Memory { kind, memory, .. } = cell {
+            if let Some(contents) = self.memories.get(memory as usize) {
+                self.place_memory(x, y, kind, contents.clone());
+            }
+            return;
+        }
+        let [width, height] = self.block(&cell).map_or([1, 1], |(_, size)| size);
+        if x + width > self.width || y + height > self.height {
+            return;
+        }
+        self.fill(x, y, cell, [width, height]);
+    }
+    /// Puts down a new [`Cell::Memory`] block with the given contents like
+    /// [`Grid::place`], see [`Memory::new`] for the sizes. The contents are
+    /// dropped again when the block is erased. Returns false if it doesn't
+    /// fit.
+    pub fn place_memory(&mut self, x: usize, y: usize, kind: MemoryKind, memory: Memory) -> bool {
+        let [width, height] = memory.size(kind);
+        if x + width > self.width || y + height > self.height {
+            return false;
+        }
+        // erasing first, it may drop other memories and renumber them
+        for dy in 0..height {
+            for dx in 0..width {
+                self.erase(x + dx, y + dy);
+            }
+        }
+        self.memories.push(memory);
+        self.edited();
+        let cell = Cell::Memory {
+            active: false,
+            kind,
+            memory: (self.memories.len() - 1) as u16,
+            x: 0,
+            y: 0,
+        };
+        self.fill(x, y, cell, [width, height]);
+        true
+    }
+    /// Replaces the cells of a block with the parts of the cell.
+    fn fill(&mut self, x: usize, y: usize, cell: Cell, [width, height]: [usize; 2]) {
+        for dy in 0..height {
+            for dx in 0..width {
+                self.erase(x + dx, y + dy);
+                self[[x + dx, y + dy]] = match cell {
+                    Cell::Display { kind, .. } => Cell::Display {
+                        active: false,
+                        kind,
+                        x: dx as u8,
+                        y: dy as u8,
+                    },
+                    Cell::Memory { kind, memory, .. } => Cell::Memory {
+                        active: false,
+                        kind,
+                        memory,
+                        x: dx as u8,
+                        y: dy as u8,
+                    },
+                    cell => cell,
+                };
+            }
+        }
+    }
+    /// The position of a cell inside the [`Cell::Display`] or
+    /// [`Cell::Memory`] it is part of and the size of the whole block.
+    fn block(&self, cell: &Cell) -> Option<([usize; 2], [usize; 2])> {
+        match *cell {
+            Cell::Display { kind, x, y, .. } => Some(([x as usize, y as usize], kind.size())),
+            Cell::Memory {
+                kind, memory, x, y, ..
+            } => {
+                let size = self.memories.get(memory as usize)?.size(kind);
+                Some(([x as usize, y as usize], size))
+            }
+            _ => None,
+        }
+    }
+    /// Empties a cell, or the whole [`Cell::Display`], [`Cell::Memory`] or
+    /// [`Instance`] it is part of.
+    pub fn erase(&mut self, x: usize, y: usize) {
+        let Some(&cell) = self.get(x, y) else {
+            return;
+        };
+        if let Some(instance) = self.instance_at(x, y) {
+            self.remove_instance(instance);
+            return;
+        }
+        self[[x, y]] = Cell::Empty;
+        if let Some(([dx, dy], [width, height])) = self.block(&cell) {
+            let left = x.saturating_sub(dx);
+            let top = y.saturating_sub(dy);
+            for y in top..(top + height).min(self.height) {
+                for x in left..(left + width).min(self.width) {
+                    let same_block = match (cell, self[[x, y]]) {
+                        (Cell::Display { kind, .. }, Cell::Display { kind: k, .. }) => kind == k,
+                        (Cell::Memory { memory, .. }, Cell::Memory { memory: m, .. }) => {
+                            memory == m
+                        }
+                        _ => false,
+                    };
+                    let part = self.block(&self[[x, y]]).map(|(pos, _)| pos);
+                    if same_block && part == Some([x - left, y - top]) {
+                        self[[x, y]] = Cell::Empty;
+                    }
+                }
+            }
+        }
+        if let Cell::Memory { memory, .. } = cell {
+            self.release_memory(memory);
+        }
+    }
+    /// Drops the contents of a memory if no block uses them anymore, the
+    /// memories after it move down.
+    fn release_memory(&mut self, memory: u16) {
+        let uses = |cell: &Cell, memory: u16| matches!(*cell, Cell::Memory { memory: m, .. } if m == memory);
+        if memory as usize >= self.memories.len()
+            || self.filled().any(|(_, cell)| uses(cell, memory))
+        {
+            return;
+        }
+        self.memories.remove(memory as usize);
+        let moved: Vec<[usize; 2]> = self
+            .filled()
+            .filter(|(_, cell)| matches!(cell, Cell::Memory { memory: m, .. } if *m > memory))
+            .map(|(pos, _)| pos)
+            .collect();
+        for pos in moved {
+            if let Cell::Memory { memory, .. } = self.cells.get_mut(pos) {
+                *memory -= 1;
+            }
+        }
+        self.edited();
+    }
+    /// The kind and inputs of the [`Cell::Display`] with its top left corner
+    /// at the given position, one bit per input.
+    pub fn display(&self, x: usize, y: usize) -> Option<(DisplayKind, u8)> {
+        let Some(&Cell::Display {
+            kind, x: 0, y: 0, ..
+        }) = self.get(x, y)
+        else {
+            return None;
+        };
+        let inputs = (0..kind.inputs()).fold(0, |inputs, i| match self.get(x, y + i) {
+            Some(&Cell::Display {
+                active: true,
+                kind: k,
+                x: 0,
+                y: dy,
+            }) if k == kind && dy as usize == i => inputs | 1 << i,
+            _ => inputs,
+        });
+        Some((kind, inputs))
+    }
+    /// The contents of a [`Cell::Memory`] of this grid.
+    pub fn memory(&self, cell: &Cell) -> Option<&Memory> {
+        match cell {
+            Cell::Memory { memory, .. } => self.memories.get(*memory as usize),
+            _ => None,
+        }
+    }
+    /// The address latched by the memory block with its top left corner at
+    /// the given position.
+    pub fn memory_address(&self, x: usize, y: usize) -> Option<usize> {
+        let cell = self.get(x, y)?;
+        let ([0, 0], [_, height]) = self.block(cell)? else {
+            return None;
+        };
+        let contents = self.memory(cell)?;
+        let inputs = (0..height).fold(0, |inputs, i| {
+            let active = self.get(x, y + i).is_some_and(|cell| cell.is_active());
+            inputs | (active as u32) << i
+        });
+        Some(contents.address(inputs))
+    }
+    /// A [`Cell::Pin`] with the given name, ready to be put into this grid.
+    /// `None` if the grid already has as many names as a pin can refer to.
+    /// Names no pin uses are only dropped when saving.
+    pub fn pin(&mut self, name: &str, kind: PinKind, direction: Direction) -> Option<Cell> {
+        let index = match self.pin_names.iter().position(|n| n == name) {
+            Some(index) => index,
+            None if self.pin_names.len() > u16::MAX as usize => return None,
+            None => {
+                self.pin_names.push(name.to_string());
+                self.pin_names.len() - 1
+            }
+        };
+        Some(Cell::Pin {
+            active: false,
+            direction,
+            kind,
+            name: u16::try_from(index).ok()?,
+        })
+    }
+    /// The pin names some cell uses, and for every pin name the index it has
+    /// among those.
+    fn used_pin_names(&self) -> (Vec<&str>, Vec<u16>) {
+        let mut used = vec![false; self.pin_names.len()];
+        for (_, cell) in self {
+            if let Cell::Pin { name, .. } = cell {
+                if let Some(used) = used.get_mut(*name as usize) {
+                    *used = true;
+                }
+            }
+        }
+        let mut names = Vec::new();
+        let mut indices = Vec::new();
+        for (name, used) in self.pin_names.iter().zip(used) {
+            indices.push(names.len() as u16);
+            if used {
+                names.push(name.as_str());
+            }
+        }
+        (names, indices)
+    }
+    /// The name of a [`Cell::Pin`] of this grid.
+    pub fn pin_name(&self, cell: &Cell) -> Option<&str> {
+        match cell {
+            Cell::Pin { name, .. } => self.pin_names.get(*name as usize).map(|n| n.as_str()),
+            _ => None,
+        }
+    }
+    /// Position, kind and name of every [`Cell::Pin`], row by row.
+    pub fn pins(&self) -> impl Iterator<Item = ([usize; 2], PinKind, &str)> + '_ {
+        self.into_iter().filter_map(|(pos, cell)| match cell {
+            Cell::Pin { kind, .. } => Some((pos, *kind, self.pin_name(cell)?)),
+            _ => None,
+        })
+    }
+    /// Sets every input pin with the given name, they keep the state while
+    /// simulating. Returns whether there is such a pin.
+    pub fn set_input(&mut self, name: &str, active: bool) -> bool {
+        let pins: Vec<[usize; 2]> = self
+            .pins()
+            .filter(|(_, kind, n)| *kind == PinKind::In && *n == name)
+            .map(|(pos, _, _)| pos)
+            .collect();
+        for &[x, y] in &pins {
+            self.set_active(x, y, active);
+        }
+        !pins.is_empty()
+    }
+    /// The state of the output pins with the given name, active if any of
+    /// them is. `None` if there is no such pin.
+    pub fn output(&self, name: &str) -> Option<bool> {
+        self.pins()
+            .filter(|(_, kind, n)| *kind == PinKind::Out && *n == name)
+            .map(|([x, y], _, _)| self[[x, y]].is_active())
+            .reduce(|a, b| a || b)
+    }
+    /// Copies a region of the grid into a new [`Module`], its pins keep their
+    /// names. Defining a module with the name of an existing one replaces
+    /// that one and updates all of its instances, see [`Grid::set_module`].
+    /// Instances inside the region become plain cells of the module.
+    pub fn define_module(
+        &mut self,
+        name: &str,
+        [x, y]: [usize; 2],
+        [width, height]: [usize; 2],
+    ) -> Option<usize> {
+        if width == 0 || height == 0 || x + width > self.width || y + height > self.height {
+            return None;
+        }
+        let mut grid = Grid::new(width, height);
+        let mut memories = HashMap::new();
+        for dy in 0..height {
+            for dx in 0..width {
+                grid[[dx, dy]] = grid.import(self, self[[x + dx, y + dy]], &mut memories);
+            }
+        }
+        match self.module(name) {
+            Some(module) => {
+                self.set_module(module, grid);
+                Some(module)
+            }
+            None => {
+                self.modules.push(Module {
+                    name: name.to_string(),
+                    grid,
+                });
+                Some(self.modules.len() - 1)
+            }
+        }
+    }
+    /// Replaces the definition of a module and copies it into all of its
+    /// instances, which resets their state. Instances that don't fit into the
+    /// grid anymore are removed.
+    pub fn set_module(&mut self, module: usize, grid: Grid) {
+        let instances: Vec<Instance> = self
+            .instances
+            .iter()
+            .copied()
+            .filter(|instance| instance.module == module)
+            .collect();
+        for instance in &instances {
+            self.erase(instance.pos[0], instance.pos[1]);
+        }
+        self.modules[module].grid = grid;
+        for instance in instances {
+            self.place_module(instance.pos[0], instance.pos[1], module);
+        }
+    }
+    pub fn modules(&self) -> &[Module] {
+        &self.modules
+    }
+    pub fn module(&self, name: &str) -> Option<usize> {
+        self.modules.iter().position(|module| module.name == name)
+    }
+    pub fn instances(&self) -> &[Instance] {
+        &self.instances
+    }
+    /// The size of an instance of the module.
+    pub fn module_size(&self, module: usize) -> [usize; 2] {
+        let grid = &self.modules[module].grid;
+        [grid.width, grid.height]
+    }
+    /// The instance covering the given cell.
+    pub fn instance_at(&self, x: usize, y: usize) -> Option<usize> {
+        self.instances.iter().position(|instance| {
+            let [width, height] = self.module_size(instance.module);
+            let [left, top] = instance.pos;
+            (left..left + width).contains(&x) && (top..top + height).contains(&y)
+        })
+    }
+    /// Puts an instance of a module into the grid with its top left corner at
+    /// the given position, removing whatever it covers. Returns false if it
+    /// doesn't fit.
+    pub fn place_module(&mut self, x: usize, y: usize, module: usize) -> bool {
+        let [width, height] = self.module_size(module);
+        if x + width > self.width || y + height > self.height {
+            return false;
+        }
+        let grid = self.modules[module].grid.clone();
+        let mut memories = HashMap::new();
+        for dy in 0..height {
+            for dx in 0..width {
+                self.erase(x + dx, y + dy);
+                self[[x + dx, y + dy]] = match grid[[dx, dy]] {
+                    Cell::Pin {
+                        active, direction, ..
+                    } => Cell::Cable { active, direction },
+                    cell => self.import(&grid, cell, &mut memories),
+                };
+            }
+        }
+        self.instances.push(Instance {
+            module,
+            pos: [x, y],
+        });
+        true
+    }
+    /// Replaces an instance with the cells of its module including the pins,
+    /// so it can be edited and defined again.
+    pub fn open_instance(&mut self, instance: usize) {
+        let Instance {
+            module,
+            pos: [x, y],
+        } = self.remove_instance(instance);
+        let grid = self.modules[module].grid.clone();
+        let mut memories = HashMap::new();
+        for ([dx, dy], cell) in &grid {
+            self[[x + dx, y + dy]] = self.import(&grid, *cell, &mut memories);
+        }
+    }
+    /// A cell of another grid ready to be put into this one, pins keep their
+    /// names and memories are copied. `memories` maps the memories of the
+    /// other grid to their copies.
+    fn import(&mut self, from: &Grid, cell: Cell, memories: &mut HashMap<u16, u16>) -> Cell {
+        if let Cell::Memory {
+            active,
+            kind,
+            memory,
+            x,
+            y,
+        } = cell
+        {
+            let Some(contents) = from.memories.get(memory as usize) else {
+                return Cell::Empty;
+            };
+            let memory = *memories.entry(memory).or_insert_with(|| {
+                self.memories.push(contents.clone());
+                self.edited();
+                (self.memories.len() - 1) as u16
+            });
+            return Cell::Memory {
+                active,
+                kind,
+                memory,
+                x,
+                y,
+            };
+        }
+        match (cell, from.pin_name(&cell)) {
+            (
+                Cell::Pin {
+                    active,
+                    direction,
+                    kind,
+                    ..
+                },
+                Some(name),
+            ) => {
+                // without a name left the pin can't be kept
+                let Some(mut pin) = self.pin(name, kind, direction) else {
+                    return Cell::Empty;
+                };
+                pin.set(active);
+                pin
+            }
+            (cell, _) => cell,
+        }
+    }
+    /// Removes an instance and empties the cells it covered.
+    fn remove_instance(&mut self, instance: usize) -> Instance {
+        let removed = self.instances.remove(instance);
+        let [width, height] = self.module_size(removed.module);
+        let [x, y] = removed.pos;
+        let mut memories = Vec::new();
+        for dy in 0..height {
+            for dx in 0..width {
+                if let Cell::Memory { memory, .. } = self[[x + dx, y + dy]] {
+                    memories.push(memory);
+                }
+                self[[x + dx, y + dy]] = Cell::Empty;
+            }
+        }
+        // the instance had its own copies of the memories of the module
+        memories.sort_unstable();
+        memories.dedup();
+        for memory in memories.into_iter().rev() {
+            self.release_memory(memory);
+        }
+        removed
+    }
     pub fn get(&self, x: usize, y: usize) -> Option<&Cell> {
-        if x > self.width {
+        (x < self.width && y < self.height).then(|| self.cells.get([x, y]))
+    }
+    /// `None` outside of the grid and for the cells of an [`Instance`], which
+    /// only change with their module.
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut Cell> {
+        if x >= self.width || y >= self.height || self.instance_at(x, y).is_some() {
             return None;
         }
-        self.cells.get(x + y * self.width)
+        self.edited();
+        Some(self.cells.get_mut([x, y]))
     }
+    /// Starts with `u32::MAX` to tell it apart from the older format, which
+    /// had every cell after the height and width. Then the height, width and
+    /// the number of chunks, all `u32`, and every chunk that isn't empty as
+    /// the x and y of its top left cell and its cells row by row.
+    ///
+    /// The cells are followed by tables, each one is left out together with
+    /// the ones after it if they are all empty. First the pin names, a `u32`
+    /// count and then every name as a `u32` length and UTF-8. Then the
+    /// modules, a `u32` count, every module as its name and its serialized
+    /// grid, both with a `u32` length, and the instances as a `u32` count and
+    /// the module, x and y of each as `u32`s. Last the memories, a `u32` count
+    /// and for every memory its address bits, data bits, last clock input and
+    /// all of its words as single bytes.
+    ///
+    /// Pin names no cell uses are left out.
     pub fn serialize(&self) -> impl Iterator<Item = u8> + '_ {
-        (self.height as u32)
+        let (names, indices) = self.used_pin_names();
+        let tables = self.serialize_tables(&names);
+        let chunks: Vec<_> = self
+            .cells
+            .iter()
+            .filter(|(_, chunk)| chunk.iter().any(|cell| *cell != Cell::Empty))
+            .collect();
+        u32::MAX
             .to_le_bytes()
             .into_iter()
+            .chain((self.height as u32).to_le_bytes())
             .chain((self.width as u32).to_le_bytes())
-            .chain(self.cells.iter().map(|c| u8::from(*c)))
+            .chain((chunks.len() as u32).to_le_bytes())
+            .chain(chunks.into_iter().flat_map(move |([x, y], chunk)| {
+                let cells: Vec<u8> = (chunk.iter())
+                    .flat_map(|&c| {
+                        let c = match c {
+                            Cell::Pin {
+                                active,
+                                direction,
+                                kind,
+                                name,
+                            } => Cell::Pin {
+                                active,
+                                direction,
+                                kind,
+                                name: indices.get(name as usize).copied().unwrap_or(name),
+                            },
+                            c => c,
+                        };
+                        std::iter::once(u8::from(c)).chain(c.extra_bytes())
+                    })
+                    .collect();
+                (x as u32)
+                    .to_le_bytes()
+                    .into_iter()
+                    .chain((y as u32).to_le_bytes())
+                    .chain(cells)
+            }))
+            .chain(tables)
+    }
+    fn serialize_tables(&self, pin_names: &[&str]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        if pin_names.is_empty() && self.modules.is_empty() && self.memories.is_empty() {
+            return bytes;
+        }
+        let with_len = |bytes: &mut Vec<u8>, data: &[u8]| {
+            bytes.extend((data.len() as u32).to_le_bytes());
+            bytes.extend(data);
+        };
+        bytes.extend((pin_names.len() as u32).to_le_bytes());
+        for name in pin_names {
+            with_len(&mut bytes, name.as_bytes());
+        }
+        if self.modules.is_empty() && self.memories.is_empty() {
+            return bytes;
+        }
+        bytes.extend((self.modules.len() as u32).to_le_bytes());
+        for module in &self.modules {
+            with_len(&mut bytes, module.name.as_bytes());
+            with_len(&mut bytes, &module.grid.serialize().collect::<Vec<u8>>());
+        }
+        bytes.extend((self.instances.len() as u32).to_le_bytes());
+        for instance in &self.instances {
+            for n in [instance.module, instance.pos[0], instance.pos[1]] {
+                bytes.extend((n as u32).to_le_bytes());
+            }
+        }
+        if self.memories.is_empty() {
+            return bytes;
+        }
+        bytes.extend((self.memories.len() as u32).to_le_bytes());
+        for memory in &self.memories {
+            bytes.extend([memory.address_bits, memory.data_bits, memory.clock as u8]);
+            bytes.extend(&memory.words);
+        }
+        bytes
     }
     pub fn deserialize(mut bytes: impl Iterator<Item = u8>) -> Result<Self, GridParseError> {
-        let mut f = || -> Option<(u32, u32)> {
-            let width =
-                u32::from_le_bytes([bytes.next()?, bytes.next()?, bytes.next()?, bytes.next()?]);
-            let height =
-                u32::from_le_bytes([bytes.next()?, bytes.next()?, bytes.next()?, bytes.next()?]);
-            Some((width, height))
-        };
-        let (width, height) = f().ok_or(GridParseError::InputTooShort)?;
-        let width = width as usize;
-        let height = height as usize;
-        let cells: Vec<Cell> = bytes
-            .take(width * height)
-            .map(Cell::try_from)
-            .collect::<Result<Vec<Cell>, CellParseError>>()?;
-        if cells.len() != width * height {
-            return Err(GridParseError::InputTooShort);
-        }
-        Ok(Self {
-            width,
-            height,
-            next: cells.clone(),
-            cells,
-            marker: 1,
-        })
+        let u32 = |bytes: &mut dyn Iterator<Item = u8>| {
+            let le = [bytes.next()?, bytes.next()?, bytes.next()?, bytes.next()?];
+            Some(u32::from_le_bytes(le))
+        };
+        let mut header = || -> Option<(u32, u32, Option<u32>)> {
+            let chunked = u32(&mut bytes)?;
+            match chunked {
+                u32::MAX => Some((u32(&mut bytes)?, u32(&mut bytes)?, Some(u32(&mut bytes)?))),
+                height => Some((height, u32(&mut bytes)?, None)),
+            }
+        };
+        let (height, width, chunks) = header().ok_or(GridParseError::InputTooShort)?;
+        let (width, height) = (width as usize, height as usize);
+        let mut grid = Self::new(width, height);
+        let mut read_cell = |bytes: &mut dyn Iterator<Item = u8>, [x, y]: [usize; 2]| {
+            let mut cell = Cell::try_from(bytes.next().ok_or(GridParseError::InputTooShort)?)?;
+            cell.read_extra_bytes(bytes)?;
+            if cell != Cell::Empty {
+                *grid.get_mut(x, y).ok_or(GridParseError::InvalidChunk)? = cell;
+            }
+            Ok::<_, GridParseError>(())
+        };
+        match chunks {
+            None => {
+                for i in 0..width * height {
+                    read_cell(&mut bytes, [i % width, i / width])?;
+                }
+            }
+            Some(chunks) => {
+                for _ in 0..chunks {
+                    let x = u32(&mut bytes).ok_or(GridParseError::InputTooShort)? as usize;
+                    let y = u32(&mut bytes).ok_or(GridParseError::InputTooShort)? as usize;
+                    for i in 0..CHUNK_SIZE * CHUNK_SIZE {
+                        read_cell(&mut bytes, [x + i % CHUNK_SIZE, y + i / CHUNK_SIZE])?;
+                    }
+                }
+            }
+        }
+        let with_len = |bytes: &mut dyn Iterator<Item = u8>| {
+            let len = u32(bytes).ok_or(GridParseError::InputTooShort)? as usize;
+            let data: Vec<u8> = bytes.take(len).collect();
+            match data.len() == len {
+                true => Ok(data),
+                false => Err(GridParseError::InputTooShort),
+            }
+        };
+        // older saves end after the cells or the pin names
+        if let Some(count) = u32(&mut bytes) {
+            for _ in 0..count {
+                let name = String::from_utf8(with_len(&mut bytes)?)
+                    .map_err(|_| GridParseError::InvalidPinName)?;
+                grid.pin_names.push(name);
+            }
+        }
+        if let Some(count) = u32(&mut bytes) {
+            for _ in 0..count {
+                let name = String::from_utf8(with_len(&mut bytes)?)
+                    .map_err(|_| GridParseError::InvalidModule)?;
+                let module = Self::deserialize(with_len(&mut bytes)?.into_iter())?;
+                grid.modules.push(Module { name, grid: module });
+            }
+            let count = u32(&mut bytes).ok_or(GridParseError::InputTooShort)?;
+            for _ in 0..count {
+                let mut n = || u32(&mut bytes).ok_or(GridParseError::InputTooShort);
+                let (module, x, y) = (n()? as usize, n()? as usize, n()? as usize);
+                if module >= grid.modules.len() {
+                    return Err(GridParseError::InvalidModule);
+                }
+                let [width, height] = grid.module_size(module);
+                if x + width > grid.width || y + height > grid.height {
+                    return Err(GridParseError::InvalidModule);
+                }
+                grid.instances.push(Instance {
+                    module,
+                    pos: [x, y],
+                });
+            }
+        }
+        if let Some(count) = u32(&mut bytes) {
+            for _ in 0..count {
+                let mut next = || bytes.next().ok_or(GridParseError::InputTooShort);
+                let (address_bits, data_bits, clock) = (next()?, next()?, next()?);
+                let len = 1 << address_bits.clamp(1, Memory::MAX_ADDRESS_BITS);
+                let words: Vec<u8> = bytes.by_ref().take(len).collect();
+                if words.len() != len {
+                    return Err(GridParseError::InputTooShort);
+                }
+                let mut memory = Memory::new(address_bits, data_bits, &words);
+                memory.clock = clock & 1 == 1;
+                grid.memories.push(memory);
+            }
+        }
+        // like instances, memory blocks have to fit into the grid
+        let invalid_memory = |[x, y]: [usize; 2], cell: &Cell| match cell {
+            Cell::Memory { .. } => match grid.block(cell) {
+                Some(([dx, dy], [width, height])) => {
+                    dx >= width
+                        || dy >= height
+                        || dx > x
+                        || dy > y
+                        || x - dx + width > grid.width
+                        || y - dy + height > grid.height
+                }
+                None => true,
+            },
+            _ => false,
+        };
+        if grid
+            .into_iter()
+            .any(|(pos, cell)| invalid_memory(pos, cell))
+        {
+            return Err(GridParseError::InvalidMemory);
+        }
+        // older saves kept the contents of erased blocks
+        for memory in (0..grid.memories.len()).rev() {
+            grid.release_memory(memory as u16);
+        }
+        Ok(grid)
+    }
+    /// Text form of [`Grid::serialize`] used for the `save` URL parameter,
+    /// every byte is shifted by 33 to skip the control characters.
+    pub fn to_save_string(&self) -> String {
+        self.serialize()
+            .map(|b| char::from_u32(b as u32 + 33).unwrap())
+            .collect()
+    }
+    pub fn from_save_string(save: &str) -> Result<Self, GridParseError> {
+        let bytes = save
+            .chars()
+            .map(|c| {
+                (c as u32)
+                    .checked_sub(33)
+                    .and_then(|b| u8::try_from(b).ok())
+                    .ok_or(GridParseError::InvalidCharacter(c))
+            })
+            .collect::<Result<Vec<u8>, GridParseError>>()?;
+        Self::deserialize(bytes.into_iter())
+    }
+}
+
+impl std::fmt::Display for Cell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            Cell::Empty => return write!(f, ". "),
+            Cell::Point { .. } => 'p',
+            Cell::Cable { .. } => 'c',
+            Cell::And { .. } => 'a',
+            Cell::Not { .. } => 'n',
+            Cell::Tee { .. } => 't',
+            Cell::Or { .. } => 'o',
+            Cell::Xor { .. } => 'x',
+            Cell::Nand { .. } => 'd',
+            Cell::Nor { .. } => 'r',
+            Cell::Cross { .. } => 'i',
+            Cell::Corner {
+                mirrored: false, ..
+            } => 'l',
+            Cell::Corner { mirrored: true, .. } => 'j',
+            Cell::Delay { .. } => 'y',
+            Cell::DFlipFlop { .. } => 'f',
+            Cell::SrLatch { .. } => 's',
+            Cell::Clock { .. } => 'k',
+            Cell::Switch { .. } => 'w',
+            Cell::Button { .. } => 'b',
+            Cell::Lamp { .. } => 'm',
+            Cell::Display { .. } => 'h',
+            Cell::Pin {
+                kind: PinKind::In, ..
+            } => 'e',
+            Cell::Pin {
+                kind: PinKind::Out, ..
+            } => 'u',
+            Cell::Memory { .. } => 'g',
+        };
+        let kind = match self.is_active() {
+            true => kind.to_ascii_uppercase(),
+            false => kind,
+        };
+        let direction = match self.direction() {
+            None => ' ',
+            Some(Direction::Up) => '^',
+            Some(Direction::Right) => '>',
+            Some(Direction::Down) => 'v',
+            Some(Direction::Left) => '<',
+        };
+        write!(f, "{kind}{direction}")
+    }
+}
+
+/// One line per row, two characters per cell. The first is the kind of the
+/// cell, upper case if the cell is active, the second its direction.
+impl std::fmt::Display for Grid {
+    /// Unbounded grids are only written up to their [`Grid::extent`].
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let [width, height] = match self.is_unbounded() {
+            true => self.extent(),
+            false => [self.width, self.height],
+        };
+        for y in 0..height {
+            for x in 0..width {
+                write!(f, "{}", self[[x, y]])?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
     }
 }
 
 #[derive(Debug, Clone, Copy)]
 pub enum GridParseError {
     InputTooShort,
+    InvalidCharacter(char),
     CellParseError(CellParseError),
-    MoreCellsExpected { expected: u32 },
+    InvalidPinName,
+    /// A module with a name that isn't UTF-8 or an instance of a module that
+    /// doesn't exist or doesn't fit into the grid.
+    InvalidModule,
+    /// A [`Cell::Memory`] without contents.
+    InvalidMemory,
+    /// A chunk with cells outside of the grid.
+    InvalidChunk,
+    MoreCellsExpected {
+        expected: u32,
+    },
 }
 
 impl From<CellParseError> for GridParseError {
@@ -436,13 +2318,15 @@ impl std::ops::Index<[usize; 2]> for Grid {
     type Output = Cell;
 
     fn index(&self, index: [usize; 2]) -> &Self::Output {
-        &self.cells[index[0] + index[1] * self.width]
+        self.get(index[0], index[1])
+            .expect("cell outside of the grid")
     }
 }
 
 impl std::ops::IndexMut<[usize; 2]> for Grid {
     fn index_mut(&mut self, index: [usize; 2]) -> &mut Self::Output {
-        &mut self.cells[index[0] + index[1] * self.width]
+        self.get_mut(index[0], index[1])
+            .expect("cell outside of the grid or part of an instance")
     }
 }
 
@@ -450,16 +2334,91 @@ impl std::ops::Index<[i32; 2]> for Grid {
     type Output = Cell;
 
     fn index(&self, index: [i32; 2]) -> &Self::Output {
-        &self.cells[index[0] as usize + index[1] as usize * self.width]
+        &self[[index[0] as usize, index[1] as usize]]
     }
 }
 
 impl std::ops::IndexMut<[i32; 2]> for Grid {
     fn index_mut(&mut self, index: [i32; 2]) -> &mut Self::Output {
-        &mut self.cells[index[0] as usize + index[1] as usize * self.width]
+        &mut self[[index[0] as usize, index[1] as usize]]
     }
 }
 
+/// A xorshift generator, good enough to pick test circuits and input vectors
+/// but not for anything that has to be unpredictable.
+pub(crate) fn xorshift(seed: u64) -> impl FnMut() -> u64 {
+    let mut rng = seed.max(1);
+    move || {
+        rng ^= rng << 13;
+        rng ^= rng >> 7;
+        rng ^= rng << 17;
+        rng
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn random_grid(width: usize, height: usize, seed: u64) -> Grid {
+    let mut next = xorshift(seed);
+    let mut grid = Grid::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let active = next().is_multiple_of(2);
+            let direction = Direction::all()[next() as usize % 4];
+            grid[[x, y]] = match next() % 19 {
+                0 => Cell::Empty,
+                1 => Cell::Point { active },
+                2 => Cell::Cable { active, direction },
+                3 => Cell::And { active, direction },
+                4 => Cell::Not { active, direction },
+                5 => Cell::Tee { active, direction },
+                6 => Cell::Or { active, direction },
+                7 => Cell::Xor { active, direction },
+                8 => Cell::Nand { active, direction },
+                9 => Cell::Nor { active, direction },
+                10 => Cell::Cross {
+                    vertical: active,
+                    horizontal: next().is_multiple_of(2),
+                    direction,
+                },
+                11 => Cell::Corner {
+                    active,
+                    direction,
+                    mirrored: next().is_multiple_of(2),
+                },
+                13 => Cell::DFlipFlop {
+                    active,
+                    direction,
+                    clock: next().is_multiple_of(2),
+                },
+                14 => Cell::SrLatch { active, direction },
+                15 => {
+                    let mut clock = Cell::Clock {
+                        active,
+                        direction,
+                        period: 0,
+                        phase: 0,
+                        high: 0,
+                    };
+                    clock.set_clock(next() as u8 % 6, next() as u8, next() as u8 % 4);
+                    clock
+                }
+                16 => Cell::Switch { active, direction },
+                17 => Cell::Button { active, direction },
+                18 => Cell::Lamp { active },
+                _ => {
+                    let ticks = 1 + next() as u8 % 4;
+                    Cell::Delay {
+                        direction,
+                        ticks,
+                        stages: next() as u32 & u32::MAX >> (32 - ticks as u32),
+                    }
+                }
+            };
+        }
+    }
+    grid
+}
+
 #[test]
 fn grid_serialize() {
     let mut grid = Grid::new(10, 10);
@@ -469,3 +2428,783 @@ fn grid_serialize() {
     };
     assert_eq!(grid, Grid::deserialize(grid.serialize()).unwrap());
 }
+
+#[test]
+fn grid_save_string() {
+    let mut grid = Grid::new(200, 1);
+    grid[[199, 0]] = Cell::Tee {
+        active: true,
+        direction: Direction::Right,
+    };
+    let save = grid.to_save_string();
+    assert_eq!(grid, Grid::from_save_string(&save).unwrap());
+    assert!(Grid::from_save_string(" ").is_err());
+}
+
+#[test]
+fn grid_unbounded() {
+    let mut grid = Grid::unbounded();
+    let not = Cell::Not {
+        active: false,
+        direction: Direction::Right,
+    };
+    grid[[3, 2]] = not;
+    grid[[1_000_000, 7]] = not;
+    grid[[1_000_001, 7]] = not;
+    grid[[40_000_000, 9_000_000]] = not;
+    assert_eq!(grid.cells.iter().count(), 3);
+    assert_eq!(grid.extent(), [40_000_001, 9_000_001]);
+
+    let loaded = Grid::deserialize(grid.serialize()).unwrap();
+    assert!(loaded.is_unbounded());
+    assert_eq!(grid, loaded);
+    assert!(grid.serialize().count() < 4 * CHUNK_SIZE * CHUNK_SIZE);
+
+    let mut incremental = grid.clone();
+    for _ in 0..5 {
+        grid.simulate();
+        incremental.simulate_incremental();
+        assert_eq!(grid, incremental);
+    }
+    assert!(grid.get(1_000_000, 7).unwrap().is_active());
+    assert!(!grid.get(1_000_001, 7).unwrap().is_active());
+
+    grid[[40_000_000, 9_000_000]] = Cell::Empty;
+    grid.simulate();
+    assert_eq!(grid.cells.iter().count(), 2);
+    assert_eq!(grid.get(Grid::UNBOUNDED, 0), None);
+
+    // saves from before the chunks list every cell
+    let bytes = [1, 0, 0, 0, 2, 0, 0, 0, u8::from(Cell::Empty), u8::from(not)];
+    let mut old = Grid::new(2, 1);
+    old[[1, 0]] = not;
+    assert_eq!(Grid::deserialize(bytes.into_iter()).unwrap(), old);
+}
+
+#[test]
+fn grid_serialize_non_square() {
+    let mut grid = Grid::new(7, 3);
+    grid[[6, 2]] = Cell::Not {
+        active: false,
+        direction: Direction::Down,
+    };
+    let loaded = Grid::deserialize(grid.serialize()).unwrap();
+    assert_eq!((loaded.width(), loaded.height()), (7, 3));
+    assert_eq!(grid, loaded);
+}
+
+#[test]
+fn grid_simulate_not_chain() {
+    let mut grid = Grid::new(3, 1);
+    grid[[0, 0]] = Cell::Not {
+        active: false,
+        direction: Direction::Right,
+    };
+    grid[[1, 0]] = Cell::Cable {
+        active: false,
+        direction: Direction::Right,
+    };
+    grid[[2, 0]] = Cell::Not {
+        active: false,
+        direction: Direction::Right,
+    };
+    grid.simulate();
+    assert!(grid.get(0, 0).unwrap().is_active());
+    assert!(!grid.get(1, 0).unwrap().is_active());
+    grid.simulate();
+    assert!(grid.get(1, 0).unwrap().is_active());
+    assert!(grid.get(2, 0).unwrap().is_active());
+    grid.simulate();
+    assert!(!grid.get(2, 0).unwrap().is_active());
+    assert_eq!(grid.get(3, 0), None);
+}
+
+#[test]
+fn grid_simulate_incremental() {
+    let mut next = xorshift(7);
+    for seed in 1..20 {
+        let mut full = random_grid(14, 9, seed);
+        let mut incremental = full.clone();
+        for _ in 0..40 {
+            if next().is_multiple_of(3) {
+                let (x, y) = (next() as usize % 14, next() as usize % 9);
+                let active = next().is_multiple_of(2);
+                full.set_active(x, y, active);
+                incremental.set_active(x, y, active);
+            }
+            full.simulate();
+            incremental.simulate_incremental();
+            assert_eq!(full, incremental);
+        }
+    }
+}
+
+#[test]
+fn grid_simulate_large_point_mesh() {
+    let mut grid = Grid::new(400, 400);
+    for y in 0..400 {
+        for x in 0..400 {
+            grid[[x, y]] = Cell::Point { active: false };
+        }
+    }
+    grid[[399, 399]] = Cell::Not {
+        active: false,
+        direction: Direction::Up,
+    };
+    grid.simulate();
+    assert!(!grid.get(0, 0).unwrap().is_active());
+    grid.simulate();
+    assert!(grid.get(0, 0).unwrap().is_active());
+    assert!(grid.get(398, 399).unwrap().is_active());
+}
+
+#[test]
+fn grid_two_input_gates() {
+    let gates = [
+        (
+            Cell::And {
+                active: false,
+                direction: Direction::Up,
+            },
+            [false, false, false, true],
+        ),
+        (
+            Cell::Or {
+                active: false,
+                direction: Direction::Up,
+            },
+            [false, true, true, true],
+        ),
+        (
+            Cell::Xor {
+                active: false,
+                direction: Direction::Up,
+            },
+            [false, true, true, false],
+        ),
+        (
+            Cell::Nand {
+                active: false,
+                direction: Direction::Up,
+            },
+            [true, true, true, false],
+        ),
+        (
+            Cell::Nor {
+                active: false,
+                direction: Direction::Up,
+            },
+            [true, false, false, false],
+        ),
+    ];
+    for (gate, table) in gates {
+        for (i, expected) in table.into_iter().enumerate() {
+            let mut grid = Grid::new(3, 1);
+            grid[[0, 0]] = Cell::Cable {
+                active: i & 1 == 1,
+                direction: Direction::Right,
+            };
+            grid[[1, 0]] = gate;
+            grid[[2, 0]] = Cell::Cable {
+                active: i & 2 == 2,
+                direction: Direction::Left,
+            };
+            grid.simulate();
+            assert_eq!(
+                grid.get(1, 0).unwrap().is_active(),
+                expected,
+                "{gate:?} {i}"
+            );
+        }
+    }
+    let mut grid = Grid::new(1, 1);
+    grid[[0, 0]] = Cell::Nor {
+        active: true,
+        direction: Direction::Left,
+    };
+    assert_eq!(grid, Grid::deserialize(grid.serialize()).unwrap());
+}
+
+#[test]
+fn grid_cross() {
+    let cable = |active, direction| Cell::Cable { active, direction };
+    let mut grid = Grid::new(3, 3);
+    grid[[1, 1]] = Cell::Cross {
+        vertical: false,
+        horizontal: false,
+        direction: Direction::Up,
+    };
+    grid[[1, 2]] = cable(true, Direction::Up);
+    grid[[1, 0]] = cable(false, Direction::Up);
+    grid[[0, 1]] = cable(false, Direction::Right);
+    grid[[2, 1]] = cable(false, Direction::Right);
+    let mut incremental = grid.clone();
+    grid.simulate();
+    incremental.simulate_incremental();
+    assert_eq!(
+        grid.get(1, 1),
+        Some(&Cell::Cross {
+            vertical: true,
+            horizontal: false,
+            direction: Direction::Up,
+        })
+    );
+    grid.simulate();
+    incremental.simulate_incremental();
+    assert_eq!(grid, incremental);
+    assert!(grid.get(1, 0).unwrap().is_active());
+    assert!(!grid.get(2, 1).unwrap().is_active());
+    grid.set_output(1, 1, 1, true);
+    assert_eq!(grid, Grid::deserialize(grid.serialize()).unwrap());
+    assert_eq!(
+        grid,
+        Grid::from_save_string(&grid.to_save_string()).unwrap()
+    );
+}
+
+#[test]
+fn grid_corner() {
+    for mirrored in [false, true] {
+        let mut grid = Grid::new(3, 2);
+        grid[[1, 1]] = Cell::Corner {
+            active: false,
+            direction: Direction::Up,
+            mirrored,
+        };
+        grid[[0, 1]] = Cell::Cable {
+            active: true,
+            direction: Direction::Right,
+        };
+        grid[[2, 1]] = Cell::Cable {
+            active: false,
+            direction: Direction::Left,
+        };
+        grid.simulate();
+        assert_eq!(grid.get(1, 1).unwrap().is_active(), !mirrored);
+        assert_eq!(grid, Grid::deserialize(grid.serialize()).unwrap());
+    }
+}
+
+#[test]
+fn grid_delay() {
+    let mut grid = Grid::new(3, 1);
+    grid[[0, 0]] = Cell::Cable {
+        active: true,
+        direction: Direction::Right,
+    };
+    grid[[1, 0]] = Cell::Delay {
+        direction: Direction::Right,
+        ticks: 1,
+        stages: 0,
+    };
+    grid.get_mut(1, 0).unwrap().set_delay(3);
+    let mut incremental = grid.clone();
+    let mut history = Vec::new();
+    for _ in 0..5 {
+        grid.simulate();
+        incremental.simulate_incremental();
+        assert_eq!(grid, incremental);
+        history.push(grid.get(1, 0).unwrap().is_active());
+    }
+    // the cable goes low after the first tick, its single pulse arrives 3
+    // ticks later
+    assert_eq!(history, [false, false, true, false, false]);
+    grid.get_mut(1, 0).unwrap().set(true);
+    let delay = grid.get(1, 0).unwrap();
+    assert!((0..3).all(|stage| delay.output(stage)));
+    assert_eq!(grid, Grid::deserialize(grid.serialize()).unwrap());
+}
+
+#[test]
+fn grid_flip_flop_and_latch() {
+    // data and set come from the left, clock and reset from the right
+    let run = |cell: Cell, inputs: &[(bool, bool)]| {
+        let mut grid = Grid::new(3, 1);
+        grid[[1, 0]] = cell;
+        grid[[0, 0]] = Cell::Cable {
+            active: false,
+            direction: Direction::Right,
+        };
+        grid[[2, 0]] = Cell::Cable {
+            active: false,
+            direction: Direction::Left,
+        };
+        let mut incremental = grid.clone();
+        let mut outputs = Vec::new();
+        for &(left, right) in inputs {
+            for grid in [&mut grid, &mut incremental] {
+                grid.set_active(0, 0, left);
+                grid.set_active(2, 0, right);
+            }
+            grid.simulate();
+            incremental.simulate_incremental();
+            assert_eq!(grid, incremental);
+            outputs.push(grid.get(1, 0).unwrap().is_active());
+        }
+        assert_eq!(grid, Grid::deserialize(grid.serialize()).unwrap());
+        outputs
+    };
+    let flip_flop = Cell::DFlipFlop {
+        active: false,
+        direction: Direction::Up,
+        clock: false,
+    };
+    let inputs = [
+        (true, false),
+        (true, true),
+        (false, true),
+        (false, false),
+        (false, true),
+        (true, true),
+    ];
+    assert_eq!(
+        run(flip_flop, &inputs),
+        [false, true, true, true, false, false]
+    );
+    let latch = Cell::SrLatch {
+        active: false,
+        direction: Direction::Up,
+    };
+    let inputs = [
+        (false, false),
+        (true, false),
+        (false, false),
+        (false, true),
+        (false, false),
+        (true, true),
+    ];
+    assert_eq!(run(latch, &inputs), [false, true, true, false, false, true]);
+}
+
+#[test]
+fn grid_clock() {
+    let mut grid = Grid::new(1, 1);
+    grid[[0, 0]] = Cell::Clock {
+        active: false,
+        direction: Direction::Up,
+        period: 2,
+        phase: 0,
+        high: 1,
+    };
+    grid.get_mut(0, 0).unwrap().set_clock(4, 1, 2);
+    let mut incremental = grid.clone();
+    let mut history = Vec::new();
+    for _ in 0..6 {
+        grid.simulate();
+        incremental.simulate_incremental();
+        assert_eq!(grid, incremental);
+        history.push(grid.get(0, 0).unwrap().is_active());
+    }
+    assert_eq!(grid.tick(), 6);
+    assert_eq!(history, [false, false, true, true, false, false]);
+    assert_eq!(grid, Grid::deserialize(grid.serialize()).unwrap());
+}
+
+#[test]
+fn grid_switches() {
+    let mut grid = Grid::new(2, 2);
+    grid[[0, 0]] = Cell::Switch {
+        active: false,
+        direction: Direction::Right,
+    };
+    grid[[1, 0]] = Cell::Cable {
+        active: false,
+        direction: Direction::Right,
+    };
+    grid[[0, 1]] = Cell::Button {
+        active: false,
+        direction: Direction::Up,
+    };
+    assert_eq!(grid.switches().collect::<Vec<_>>(), [[0, 0], [0, 1]]);
+    let mut incremental = grid.clone();
+    for grid in [&mut grid, &mut incremental] {
+        grid.set_active(0, 0, true);
+    }
+    for _ in 0..3 {
+        grid.simulate();
+        incremental.simulate_incremental();
+        assert_eq!(grid, incremental);
+        assert!(grid.get(0, 0).unwrap().is_active());
+        assert!(grid.get(1, 0).unwrap().is_active());
+    }
+    assert_eq!(grid, Grid::deserialize(grid.serialize()).unwrap());
+}
+
+#[test]
+fn grid_lamp_and_display() {
+    let mut grid = Grid::new(4, 5);
+    grid.place(
+        2,
+        1,
+        Cell::Display {
+            active: false,
+            kind: DisplayKind::Hex,
+            x: 0,
+            y: 0,
+        },
+    );
+    grid.place(1, 0, Cell::Lamp { active: false });
+    for y in 1..5 {
+        grid.place(
+            1,
+            y,
+            Cell::Switch {
+                active: false,
+                direction: Direction::Right,
+            },
+        );
+    }
+    grid.place(
+        0,
+        0,
+        Cell::Cable {
+            active: true,
+            direction: Direction::Right,
+        },
+    );
+    grid.set_active(1, 1, true);
+    grid.set_active(1, 4, true);
+    grid.simulate();
+    assert!(grid.get(1, 0).unwrap().is_active());
+    assert_eq!(grid.display(2, 1), Some((DisplayKind::Hex, 0b1001)));
+    assert_eq!(DisplayKind::Hex.segments(0b1001), 0x6F);
+    assert_eq!(grid.display(3, 1), None);
+    assert_eq!(grid, Grid::deserialize(grid.serialize()).unwrap());
+    grid.erase(3, 3);
+    assert!((1..5).all(|y| grid.get(2, y) == Some(&Cell::Empty)));
+}
+
+#[test]
+fn grid_pins() {
+    let mut grid = Grid::new(3, 1);
+    let input = grid.pin("a", PinKind::In, Direction::Right).unwrap();
+    let output = grid.pin("y", PinKind::Out, Direction::Right).unwrap();
+    grid.place(0, 0, input);
+    grid.place(
+        1,
+        0,
+        Cell::Not {
+            active: false,
+            direction: Direction::Right,
+        },
+    );
+    grid.place(2, 0, output);
+    assert_eq!(grid.pin_name(&grid[[0, 0]]), Some("a"));
+    assert_eq!(
+        grid.pins().collect::<Vec<_>>(),
+        [([0, 0], PinKind::In, "a"), ([2, 0], PinKind::Out, "y")]
+    );
+    for active in [true, false] {
+        assert!(grid.set_input("a", active));
+        grid.simulate();
+        grid.simulate();
+        assert_eq!(grid.output("y"), Some(!active));
+    }
+    assert_eq!(grid.output("missing"), None);
+    assert!(!grid.set_input("missing", true));
+    assert!(!grid.set_input("y", true));
+    let loaded = Grid::deserialize(grid.serialize()).unwrap();
+    assert_eq!(loaded, grid);
+    assert_eq!(loaded.pin_name(&loaded[[2, 0]]), Some("y"));
+    assert_eq!(
+        grid,
+        Grid::from_save_string(&grid.to_save_string()).unwrap()
+    );
+
+    // names no pin uses are left out of saves
+    grid.pin("unused", PinKind::In, Direction::Right).unwrap();
+    let loaded = Grid::deserialize(grid.serialize()).unwrap();
+    assert_eq!(loaded.pin_names, ["a", "y"]);
+    grid.erase(0, 0);
+    let loaded = Grid::deserialize(grid.serialize()).unwrap();
+    assert_eq!(loaded.pin_names, ["y"]);
+    assert_eq!(loaded.pin_name(&loaded[[2, 0]]), Some("y"));
+    grid.pin_names.resize(u16::MAX as usize + 1, String::new());
+    assert_eq!(grid.pin("full", PinKind::In, Direction::Right), None);
+    assert_eq!(grid.pin_names.len(), u16::MAX as usize + 1);
+    assert!(grid.pin("y", PinKind::In, Direction::Right).is_some());
+}
+
+#[test]
+fn grid_modules() {
+    let mut grid = Grid::new(5, 2);
+    let input = grid.pin("a", PinKind::In, Direction::Right).unwrap();
+    let output = grid.pin("y", PinKind::Out, Direction::Right).unwrap();
+    grid.place(0, 0, input);
+    grid.place(
+        1,
+        0,
+        Cell::Not {
+            active: false,
+            direction: Direction::Right,
+        },
+    );
+    grid.place(2, 0, output);
+    let inverter = grid.define_module("inverter", [0, 0], [3, 1]).unwrap();
+    assert_eq!(grid.module("inverter"), Some(inverter));
+    assert_eq!(grid.define_module("too big", [3, 0], [3, 1]), None);
+    grid.place(
+        0,
+        1,
+        Cell::Switch {
+            active: false,
+            direction: Direction::Right,
+        },
+    );
+    assert!(grid.place_module(1, 1, inverter));
+    assert!(!grid.place_module(3, 0, inverter));
+    grid.place(4, 1, Cell::Lamp { active: false });
+    assert_eq!(grid.instance_at(3, 1), Some(0));
+    assert_eq!(grid.pins().count(), 2);
+    let mut incremental = grid.clone();
+    for active in [true, false] {
+        grid.set_active(0, 1, active);
+        incremental.set_active(0, 1, active);
+        for _ in 0..4 {
+            grid.simulate();
+            incremental.simulate_incremental();
+        }
+        assert_eq!(grid, incremental);
+        assert_eq!(grid.get(4, 1).unwrap().is_active(), !active);
+    }
+    assert_eq!(grid, Grid::deserialize(grid.serialize()).unwrap());
+    assert_eq!(
+        grid,
+        Grid::from_save_string(&grid.to_save_string()).unwrap()
+    );
+    // turning the definition into a buffer changes the instance too
+    grid.place(
+        1,
+        0,
+        Cell::Cable {
+            active: false,
+            direction: Direction::Right,
+        },
+    );
+    assert_eq!(
+        grid.define_module("inverter", [0, 0], [3, 1]),
+        Some(inverter)
+    );
+    assert_eq!(grid.instances().len(), 1);
+    grid.set_active(0, 1, true);
+    for _ in 0..4 {
+        grid.simulate();
+    }
+    assert!(grid.get(4, 1).unwrap().is_active());
+    grid.open_instance(0);
+    assert!(grid.instances().is_empty());
+    assert_eq!(grid.pins().filter(|(pos, _, _)| *pos == [1, 1]).count(), 1);
+    assert!(grid.place_module(1, 1, inverter));
+    // the instance can't diverge from its definition, replacing one of its
+    // cells removes the whole instance
+    assert!(grid.get_mut(2, 1).is_none());
+    grid.place(2, 1, Cell::Lamp { active: false });
+    assert!(grid.instances().is_empty());
+    assert_eq!(grid.get(1, 1), Some(&Cell::Empty));
+    assert!(grid.place_module(1, 1, inverter));
+    grid.erase(2, 1);
+    assert!(grid.instances().is_empty());
+    assert!((1..4).all(|x| grid.get(x, 1) == Some(&Cell::Empty)));
+}
+
+#[test]
+fn grid_memory() {
+    assert_eq!(Memory::parse_hex("0f, 1a 3"), Some(vec![0x0f, 0x1a, 0x03]));
+    assert_eq!(Memory::parse_hex("0g"), None);
+    let fitting = Memory::fitting(&[1, 2, 3, 4, 5]);
+    assert_eq!((fitting.address_bits(), fitting.data_bits()), (3, 3));
+    let switch = Cell::Switch {
+        active: false,
+        direction: Direction::Right,
+    };
+    // a ROM with 2 address and 2 data bits, read into two lamps
+    let mut grid = Grid::new(4, 2);
+    let rom = Memory::new(2, 2, &[0b01, 0b10, 0b11]);
+    assert!(grid.place_memory(1, 0, MemoryKind::Rom, rom));
+    let rom = grid[[1, 0]];
+    for y in 0..2 {
+        grid.place(0, y, switch);
+        grid.place(3, y, Cell::Lamp { active: false });
+    }
+    let mut incremental = grid.clone();
+    for (address, word) in [(1, 0b10), (3, 0b00), (2, 0b11)] {
+        for y in 0..2 {
+            grid.set_active(0, y, address >> y & 1 == 1);
+            incremental.set_active(0, y, address >> y & 1 == 1);
+        }
+        for _ in 0..2 {
+            grid.simulate();
+            incremental.simulate_incremental();
+        }
+        assert_eq!(grid, incremental);
+        assert_eq!(grid.memory_address(1, 0), Some(address));
+        for y in 0..2 {
+            assert_eq!(grid.get(3, y).unwrap().is_active(), word >> y & 1 == 1);
+        }
+    }
+    assert_eq!(grid, Grid::deserialize(grid.serialize()).unwrap());
+    // placing the same ROM again copies it, erasing removes the whole block
+    // and its contents
+    grid.place(2, 0, rom);
+    assert_eq!(grid.memories.len(), 1);
+    assert_eq!(grid.memory(&grid[[2, 0]]).unwrap().words(), [1, 2, 3, 0]);
+    let rom = grid[[2, 0]];
+    grid.erase(3, 1);
+    assert!((0..2).all(|y| grid.get(2, y) == Some(&Cell::Empty)));
+    assert_eq!(grid.get(1, 0), Some(&Cell::Empty));
+    assert!(grid.memories.is_empty());
+    grid.place(1, 0, rom);
+    assert_eq!(grid.get(1, 0), Some(&Cell::Empty));
+
+    // a RAM with 1 address and 1 data bit, inputs are address, data, write
+    // enable and clock
+    let mut grid = Grid::new(4, 4);
+    grid.place_memory(1, 0, MemoryKind::Ram, Memory::new(1, 1, &[]));
+    for y in 0..4 {
+        grid.place(0, y, switch);
+    }
+    grid.place(3, 0, Cell::Lamp { active: false });
+    let run = |grid: &mut Grid, inputs: [bool; 4]| {
+        for (y, active) in inputs.into_iter().enumerate() {
+            grid.set_active(0, y, active);
+        }
+        for _ in 0..2 {
+            grid.simulate_incremental();
+        }
+        grid.get(3, 0).unwrap().is_active()
+    };
+    assert!(!run(&mut grid, [true, true, true, false]));
+    assert!(run(&mut grid, [true, true, true, true]));
+    assert!(run(&mut grid, [true, false, false, false]));
+    assert!(!run(&mut grid, [false, false, false, false]));
+    // writing needs a rising clock
+    assert!(!run(&mut grid, [false, false, false, true]));
+    assert!(!run(&mut grid, [false, true, true, true]));
+    assert_eq!(grid.memory(&grid[[1, 0]]).unwrap().words(), [0, 1]);
+    assert_eq!(
+        grid,
+        Grid::from_save_string(&grid.to_save_string()).unwrap()
+    );
+
+    // instances have their own copy of the memories of the module, which
+    // goes away with the instance
+    let mut grid = Grid::new(6, 2);
+    grid.place_memory(0, 0, MemoryKind::Rom, Memory::new(1, 1, &[1]));
+    for _ in 0..5 {
+        let module = grid.define_module("rom", [0, 0], [2, 2]).unwrap();
+        grid.place_module(3, 0, module);
+    }
+    assert_eq!(grid.memories.len(), 2);
+    grid.erase(3, 0);
+    assert_eq!(grid.memories.len(), 1);
+    assert_eq!(grid.memory(&grid[[0, 0]]).unwrap().words(), [1, 0]);
+
+    // a block cut off by the edge of the grid is simulated without its
+    // outputs, but can't be loaded
+    let mut grid = Grid::new(1, 1);
+    grid.memories.push(Memory::new(1, 1, &[1]));
+    grid[[0, 0]] = Cell::Memory {
+        active: false,
+        kind: MemoryKind::Rom,
+        memory: 0,
+        x: 0,
+        y: 0,
+    };
+    grid.simulate();
+    assert!(matches!(
+        Grid::deserialize(grid.serialize()),
+        Err(GridParseError::InvalidMemory)
+    ));
+}
+
+#[test]
+fn grid_settle() {
+    let mut grid = Grid::new(3, 1);
+    for x in 0..3 {
+        grid[[x, 0]] = Cell::Not {
+            active: false,
+            direction: Direction::Right,
+        };
+    }
+    assert!(matches!(grid.settle(100), Settle::Stable { .. }));
+    assert_eq!(grid.settle(100), Settle::Stable { ticks: 1 });
+
+    // the clock keeps its state for two ticks but still isn't stable
+    let mut grid = Grid::new(2, 1);
+    grid[[0, 0]] = Cell::Clock {
+        active: false,
+        direction: Direction::Right,
+        period: 4,
+        phase: 0,
+        high: 2,
+    };
+    grid[[1, 0]] = Cell::Cable {
+        active: false,
+        direction: Direction::Right,
+    };
+    let Settle::Oscillating { period, cells, .. } = grid.settle(100) else {
+        panic!("a clock never settles");
+    };
+    assert_eq!(period, 4);
+    assert_eq!(cells, [[0, 0], [1, 0]]);
+
+    // a signal running around in a ring of corners
+    let mut grid = Grid::new(2, 2);
+    for ([x, y], direction) in [
+        ([0, 0], Direction::Right),
+        ([1, 0], Direction::Down),
+        ([1, 1], Direction::Left),
+        ([0, 1], Direction::Up),
+    ] {
+        grid[[x, y]] = Cell::Corner {
+            active: [x, y] == [0, 0],
+            direction,
+            mirrored: true,
+        };
+    }
+    assert_eq!(grid.clone().settle(2), Settle::Unsettled);
+    let Settle::Oscillating { period, cells, .. } = grid.settle(100) else {
+        panic!("the signal keeps running");
+    };
+    assert_eq!(period, 4);
+    assert_eq!(cells.len(), 4);
+}
+
+#[test]
+fn grid_resize() {
+    let not = Cell::Not {
+        active: false,
+        direction: Direction::Right,
+    };
+    let lamp = Cell::Lamp { active: false };
+    let mut grid = Grid::new(4, 3);
+    grid.place(1, 1, not);
+    grid.place(3, 2, lamp);
+    assert_eq!(grid.bounding_box(), Some(([1, 1], [3, 2])));
+
+    assert_eq!(grid.add_lines(Direction::Left, 2), [2, 0]);
+    assert_eq!((grid.width(), grid.height()), (6, 3));
+    assert_eq!(grid.get(3, 1), Some(&not));
+    assert_eq!(grid.remove_lines(Direction::Up, 1), [0, -1]);
+    assert_eq!((grid.width(), grid.height()), (6, 2));
+    assert_eq!(grid.get(5, 1), Some(&lamp));
+
+    assert_eq!(grid.crop(), [-3, 0]);
+    assert_eq!((grid.width(), grid.height()), (3, 2));
+    assert_eq!(grid.get(0, 0), Some(&not));
+    assert_eq!(grid.resize([5, 4], [Anchor::Middle; 2]), [1, 1]);
+    assert_eq!(grid.get(3, 2), Some(&lamp));
+    grid.shift([-2, 0]);
+    assert_eq!(grid.bounding_box(), Some(([1, 2], [1, 1])));
+
+    // instances cut by the edge are removed as a whole
+    let mut grid = Grid::new(3, 2);
+    grid.place(0, 0, not);
+    grid.place(1, 0, lamp);
+    let module = grid.define_module("lit", [0, 0], [2, 1]).unwrap();
+    assert!(grid.place_module(1, 1, module));
+    grid.remove_lines(Direction::Right, 1);
+    assert!(grid.instances().is_empty());
+    assert_eq!(grid.bounding_box(), Some(([0, 0], [2, 1])));
+    assert_eq!(Grid::unbounded().crop(), [0, 0]);
+}
@@ -0,0 +1,305 @@
+use crate::grid::{Cell, Grid, Memory};
+use std::collections::VecDeque;
+
+/// Recorded past states of a grid to step back to.
+///
+/// Only the current state is kept in full, the others as steps holding the
+/// cells and memories that changed between two neighbouring states, so a tick
+/// changing a few signals of a large grid costs a few bytes to keep. Recording
+/// still copies all non-empty cells to compare them. Call [`History::record`]
+/// after every tick. The oldest states are dropped to stay within the depth and
+/// memory cap. Editing the layout of the grid starts a new history, changing
+/// signals like a switch does not.
+#[derive(Debug, Clone)]
+pub struct History {
+    /// Changes from the oldest state on, oldest first.
+    steps: VecDeque<Step>,
+    /// How many steps have been applied to the oldest state to get the
+    /// current one, less than `steps.len()` after stepping back.
+    cursor: usize,
+    /// The state the grid was in when it was last recorded or restored.
+    current: Option<State>,
+    /// [`Grid::revision`] of the recorded grid.
+    revision: u64,
+    depth: usize,
+    memory_cap: usize,
+    /// Memory used by the steps.
+    bytes: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct State {
+    tick: u64,
+    /// The non-empty cells row by row, simulating never changes which cells
+    /// these are.
+    cells: Vec<([usize; 2], Cell)>,
+    memories: Vec<Memory>,
+}
+
+/// The difference between two states.
+#[derive(Debug, Clone)]
+struct Step {
+    /// Tick before and after.
+    ticks: [u64; 2],
+    /// Index into [`State::cells`] and the cell before and after.
+    cells: Vec<(usize, Cell, Cell)>,
+    /// Index of the memory and its contents before and after.
+    memories: Vec<(usize, Memory, Memory)>,
+}
+
+impl State {
+    fn of(grid: &Grid) -> Self {
+        Self {
+            tick: grid.tick(),
+            cells: grid
+                .into_iter()
+                .filter(|(_, cell)| **cell != Cell::Empty)
+                .map(|(pos, cell)| (pos, *cell))
+                .collect(),
+            memories: grid.memories().to_vec(),
+        }
+    }
+    /// Whether steps between the states can be recorded.
+    fn same_layout(&self, other: &Self) -> bool {
+        self.cells.len() == other.cells.len()
+            && self.memories.len() == other.memories.len()
+            && self.cells.iter().zip(&other.cells).all(|(a, b)| a.0 == b.0)
+    }
+}
+
+impl Step {
+    fn between(before: &State, after: &State) -> Self {
+        let cells = (before.cells.iter().zip(&after.cells).enumerate())
+            .filter(|(_, (a, b))| a.1 != b.1)
+            .map(|(i, (a, b))| (i, a.1, b.1))
+            .collect();
+        let memories = (before.memories.iter().zip(&after.memories).enumerate())
+            .filter(|(_, (a, b))| a != b)
+            .map(|(i, (a, b))| (i, a.clone(), b.clone()))
+            .collect();
+        Self {
+            ticks: [before.tick, after.tick],
+            cells,
+            memories,
+        }
+    }
+    fn apply(&self, state: &mut State, forward: bool) {
+        for &(i, before, after) in &self.cells {
+            state.cells[i].1 = if forward { after } else { before };
+        }
+        for (i, before, after) in &self.memories {
+            state.memories[*i] = if forward { after } else { before }.clone();
+        }
+        state.tick = self.ticks[forward as usize];
+    }
+    fn bytes(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + std::mem::size_of_val(&*self.cells)
+            + (self.memories.iter())
+                .map(|(_, a, b)| {
+                    2 * std::mem::size_of::<Memory>() + a.words().len() + b.words().len()
+                })
+                .sum::<usize>()
+    }
+}
+
+impl History {
+    /// Keeps at most `depth` steps using at most `memory_cap` bytes.
+    pub fn new(depth: usize, memory_cap: usize) -> Self {
+        Self {
+            steps: VecDeque::new(),
+            cursor: 0,
+            current: None,
+            revision: 0,
+            depth,
+            memory_cap,
+            bytes: 0,
+        }
+    }
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        self.trim();
+    }
+    pub fn set_memory_cap(&mut self, memory_cap: usize) {
+        self.memory_cap = memory_cap;
+        self.trim();
+    }
+    pub fn clear(&mut self) {
+        self.steps.clear();
+        self.cursor = 0;
+        self.current = None;
+        self.bytes = 0;
+    }
+    /// Adds the state of the grid as the newest one. After stepping back this
+    /// resumes from there and drops the states that were stepped over.
+    pub fn record(&mut self, grid: &Grid) {
+        let state = State::of(grid);
+        match &self.current {
+            Some(current) if self.revision == grid.revision() && current.same_layout(&state) => {
+                if *current == state {
+                    return;
+                }
+                let step = Step::between(current, &state);
+                for step in self.steps.drain(self.cursor..) {
+                    self.bytes -= step.bytes();
+                }
+                self.bytes += step.bytes();
+                self.steps.push_back(step);
+                self.cursor += 1;
+            }
+            _ => {
+                self.clear();
+                self.revision = grid.revision();
+            }
+        }
+        self.current = Some(state);
+        self.trim();
+    }
+    /// Drops the oldest steps, or the undone ones while at the oldest state,
+    /// until the limits are kept.
+    fn trim(&mut self) {
+        while self.steps.len() > self.depth
+            || (self.bytes > self.memory_cap && !self.steps.is_empty())
+        {
+            let step = match self.cursor {
+                0 => self.steps.pop_back(),
+                _ => self.steps.pop_front(),
+            };
+            self.bytes -= step.map_or(0, |step| step.bytes());
+            self.cursor = self.cursor.saturating_sub(1);
+        }
+    }
+    /// Puts the grid into the state after `cursor` steps, fails if the grid
+    /// was edited since it was recorded.
+    fn seek(&mut self, grid: &mut Grid, cursor: usize) -> bool {
+        let Some(current) = &mut self.current else {
+            return false;
+        };
+        if self.revision != grid.revision()
+            || current.memories.len() != grid.memories().len()
+            || cursor > self.steps.len()
+        {
+            return false;
+        }
+        while self.cursor > cursor {
+            self.cursor -= 1;
+            self.steps[self.cursor].apply(current, false);
+        }
+        while self.cursor < cursor {
+            self.steps[self.cursor].apply(current, true);
+            self.cursor += 1;
+        }
+        grid.restore(&current.cells, &current.memories, current.tick);
+        true
+    }
+    /// Goes back `count` recorded states, or as far as possible.
+    pub fn step_back(&mut self, grid: &mut Grid, count: usize) -> bool {
+        count > 0 && self.cursor > 0 && self.seek(grid, self.cursor.saturating_sub(count))
+    }
+    /// Goes forward `count` recorded states without simulating, or as far as
+    /// possible.
+    pub fn step_forward(&mut self, grid: &mut Grid, count: usize) -> bool {
+        let cursor = (self.cursor + count).min(self.steps.len());
+        cursor > self.cursor && self.seek(grid, cursor)
+    }
+    /// Goes to the latest recorded state at or before the tick, or the oldest
+    /// one if the tick is before that.
+    pub fn scrub(&mut self, grid: &mut Grid, tick: u64) -> bool {
+        let cursor = (1..=self.steps.len())
+            .rev()
+            .find(|&cursor| self.steps[cursor - 1].ticks[1] <= tick)
+            .unwrap_or(0);
+        self.seek(grid, cursor)
+    }
+    /// The oldest and newest recorded ticks.
+    pub fn ticks(&self) -> Option<[u64; 2]> {
+        let current = self.current.as_ref()?;
+        let oldest = self
+            .steps
+            .front()
+            .map_or(current.tick, |step| step.ticks[0]);
+        let newest = self.steps.back().map_or(current.tick, |step| step.ticks[1]);
+        Some([oldest, newest])
+    }
+    /// The tick of the state the grid was last recorded or restored in.
+    pub fn tick(&self) -> Option<u64> {
+        self.current.as_ref().map(|current| current.tick)
+    }
+    /// Whether there are states after the current one to step forward to.
+    pub fn is_rewound(&self) -> bool {
+        self.cursor < self.steps.len()
+    }
+    /// Number of recorded states.
+    pub fn len(&self) -> usize {
+        self.current.as_ref().map_or(0, |_| self.steps.len() + 1)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.current.is_none()
+    }
+    /// Memory used by the recorded changes, not counting the current state.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+#[test]
+fn history_step_back_and_resume() {
+    use crate::grid::{Direction, MemoryKind};
+    let mut grid = Grid::new(3, 1);
+    grid[[0, 0]] = Cell::Switch {
+        active: true,
+        direction: Direction::Right,
+    };
+    grid[[1, 0]] = Cell::Delay {
+        direction: Direction::Right,
+        ticks: 2,
+        stages: 0,
+    };
+    grid[[2, 0]] = Cell::Lamp { active: false };
+    let mut history = History::new(100, usize::MAX);
+    let mut states = vec![grid.clone()];
+    history.record(&grid);
+    for _ in 0..6 {
+        grid.simulate_incremental();
+        history.record(&grid);
+        states.push(grid.clone());
+    }
+    assert_eq!(history.len(), 7);
+    assert_eq!(history.ticks(), Some([0, 6]));
+
+    assert!(history.step_back(&mut grid, 2));
+    assert_eq!(grid, states[4]);
+    assert_eq!(grid.tick(), 4);
+    assert!(history.scrub(&mut grid, 1));
+    assert_eq!(grid, states[1]);
+    assert!(history.step_forward(&mut grid, 1));
+    assert_eq!(grid, states[2]);
+    assert!(history.is_rewound());
+
+    // resuming with the switch off drops the recorded future
+    grid.set_active(0, 0, false);
+    grid.simulate_incremental();
+    history.record(&grid);
+    assert!(!history.is_rewound());
+    assert_eq!(history.ticks(), Some([0, 3]));
+    history.set_depth(2);
+    assert_eq!(history.ticks(), Some([1, 3]));
+    history.set_memory_cap(0);
+    assert_eq!(history.len(), 1);
+
+    grid[[2, 0]] = Cell::Empty;
+    assert!(!history.step_back(&mut grid, 1));
+    history.record(&grid);
+    assert_eq!(history.len(), 1);
+
+    // adding a memory block is an edit too
+    history.set_depth(100);
+    history.set_memory_cap(usize::MAX);
+    for _ in 0..3 {
+        grid.simulate_incremental();
+        history.record(&grid);
+    }
+    let rom = Memory::new(1, 1, &[1]);
+    assert!(grid.place_memory(1, 0, MemoryKind::Rom, rom));
+    assert!(!history.step_back(&mut grid, 1));
+}
@@ -0,0 +1,173 @@
+use crate::{
+    grid::{Cell, Grid},
+    netlist::{Netlist, Node},
+    signal::Signal,
+};
+
+/// Simulates 64 independent copies of a circuit at once.
+///
+/// Every gate and net holds a `u64` with one bit per copy, so gates become
+/// plain bitwise operations. The copies only differ in the signals set with
+/// [`LaneSimulator::set`], which makes it possible to try all combinations of
+/// up to 6 inputs in a single run, see [`counting_lanes`].
+#[derive(Debug, Clone)]
+pub struct LaneSimulator {
+    netlist: Netlist,
+    state: Vec<u64>,
+    next: Vec<u64>,
+    net_state: Vec<u64>,
+    tick: u64,
+}
+
+impl LaneSimulator {
+    pub const LANES: usize = 64;
+
+    /// Starts all lanes in the current state of the grid. `None` if the grid
+    /// has [`Cell::Memory`] blocks, which a [`Netlist`] doesn't simulate.
+    pub fn new(grid: &Grid) -> Option<Self> {
+        match (grid.into_iter()).any(|(_, cell)| matches!(cell, Cell::Memory { .. })) {
+            true => None,
+            false => Some(Self::from_netlist(Netlist::compile(grid))),
+        }
+    }
+    /// Like [`LaneSimulator::new`], the memory blocks of the grid the netlist
+    /// was compiled from keep their outputs.
+    pub fn from_netlist(netlist: Netlist) -> Self {
+        let state: Vec<u64> = (0..netlist.gates().len())
+            .map(|g| u64::splat(netlist.is_active(Node::Gate(g))))
+            .collect();
+        let net_state = (0..netlist.nets().len())
+            .map(|n| u64::splat(netlist.is_active(Node::Net(n))))
+            .collect();
+        Self {
+            next: state.clone(),
+            state,
+            net_state,
+            tick: netlist.tick(),
+            netlist,
+        }
+    }
+    pub fn netlist(&self) -> &Netlist {
+        &self.netlist
+    }
+    pub fn step(&mut self) {
+        for net in 0..self.net_state.len() {
+            self.net_state[net] = self.netlist.net_with(net, &self.state);
+        }
+        for gate in 0..self.next.len() {
+            self.next[gate] =
+                self.netlist
+                    .eval_with(gate, self.tick + 1, &self.state, &self.net_state);
+        }
+        std::mem::swap(&mut self.state, &mut self.next);
+        self.tick += 1;
+    }
+    /// The signals of the cell at the given position in all lanes, `None` for
+    /// empty cells. Cells with several outputs are active if any of their
+    /// external outputs is.
+    pub fn get(&self, x: usize, y: usize) -> Option<u64> {
+        self.netlist.node(x, y).map(|node| match node {
+            Node::Gate(g) => self.netlist.gates()[g]
+                .cell
+                .external_outputs()
+                .fold(0, |lanes, output| lanes | self.state[g + output]),
+            Node::Net(n) => self.net_state[n],
+        })
+    }
+    /// Overrides the signals of the cell at the given position, one bit per lane.
+    pub fn set(&mut self, x: usize, y: usize, lanes: u64) {
+        match self.netlist.node(x, y) {
+            Some(Node::Gate(g)) => {
+                for output in self.netlist.gates()[g].cell.external_outputs() {
+                    self.state[g + output] = lanes;
+                }
+            }
+            Some(Node::Net(n)) => self.net_state[n] = lanes,
+            None => {}
+        }
+    }
+    /// Copies the state of a single lane into the grid the netlist was
+    /// compiled from.
+    pub fn write_lane_to(&self, lane: usize, grid: &mut Grid) {
+        let active = |lanes: u64| lanes >> lane & 1 == 1;
+        for (gate, lanes) in self.netlist.gates().iter().zip(&self.state) {
+            grid.set_output(gate.pos[0], gate.pos[1], gate.output, active(*lanes));
+        }
+        for (net, lanes) in self.netlist.nets().iter().zip(&self.net_state) {
+            for [x, y] in &net.points {
+                grid.set_active(*x, *y, active(*lanes));
+            }
+        }
+    }
+}
+
+/// Lane masks for enumerating input combinations: with input `i` set to
+/// `counting_lanes(i)`, lane `n` sees bit `i` of `n` on that input.
+pub fn counting_lanes(input: usize) -> u64 {
+    (0..LaneSimulator::LANES as u64)
+        .filter(|lane| lane >> input & 1 == 1)
+        .fold(0, |mask, lane| mask | 1 << lane)
+}
+
+#[test]
+fn lanes_match_grid_simulate() {
+    let layout = crate::grid::random_grid(12, 10, 3);
+    let mut grids: Vec<Grid> = (0..LaneSimulator::LANES as u64)
+        .map(|lane| {
+            let states = crate::grid::random_grid(12, 10, lane + 100);
+            let mut grid = layout.clone();
+            for ([x, y], cell) in &states {
+                grid.set_active(x, y, cell.is_active());
+            }
+            grid
+        })
+        .collect();
+    let mut lanes = LaneSimulator::new(&layout).unwrap();
+    for y in 0..10 {
+        for x in 0..12 {
+            let mask = grids
+                .iter()
+                .enumerate()
+                .filter(|(_, grid)| grid.get(x, y).unwrap().is_active())
+                .fold(0, |mask, (lane, _)| mask | 1 << lane);
+            lanes.set(x, y, mask);
+        }
+    }
+    for _ in 0..20 {
+        lanes.step();
+        for (lane, grid) in grids.iter_mut().enumerate() {
+            grid.simulate();
+            let mut compiled = layout.clone();
+            lanes.write_lane_to(lane, &mut compiled);
+            assert_eq!(*grid, compiled);
+        }
+    }
+}
+
+#[test]
+fn lanes_and_truth_table() {
+    use crate::grid::{Cell, Direction};
+    let mut grid = Grid::new(3, 2);
+    grid[[0, 1]] = Cell::Cable {
+        active: false,
+        direction: Direction::Right,
+    };
+    grid[[2, 1]] = Cell::Cable {
+        active: false,
+        direction: Direction::Left,
+    };
+    grid[[1, 1]] = Cell::And {
+        active: false,
+        direction: Direction::Up,
+    };
+    let mut lanes = LaneSimulator::new(&grid).unwrap();
+    lanes.set(0, 1, counting_lanes(0));
+    lanes.set(2, 1, counting_lanes(1));
+    lanes.step();
+    assert_eq!(counting_lanes(0), 0xAAAA_AAAA_AAAA_AAAA);
+    assert_eq!(lanes.get(1, 1), Some(0x8888_8888_8888_8888));
+
+    let rom = crate::grid::Memory::new(1, 1, &[1]);
+    assert!(grid.place_memory(1, 0, crate::grid::MemoryKind::Rom, rom));
+    assert!(LaneSimulator::new(&grid).is_none());
+}
@@ -1,18 +1,49 @@
+#[cfg(feature = "web")]
+mod analyzer;
+#[cfg(feature = "web")]
 mod canvas;
+mod chunks;
+pub mod equivalence;
+#[cfg(feature = "web")]
 mod event_loop;
-mod grid;
+pub mod grid;
+pub mod history;
+#[cfg(feature = "web")]
 mod image;
+pub mod lanes;
+pub mod netlist;
+mod nets;
+pub mod signal;
+#[cfg(feature = "web")]
 mod state;
+pub mod truth_table;
+#[cfg(feature = "web")]
+#[allow(dead_code)]
 mod util;
+pub mod waveform;
+pub use equivalence::{Counterexample, Equivalence, EquivalenceCheck, EquivalenceError, Mismatch};
+pub use grid::{
+    Anchor, Cell, CellParseError, Direction, DisplayKind, Grid, GridParseError, Input, Instance,
+    Memory, MemoryKind, Module, PinKind, Settle,
+};
+pub use history::History;
+pub use lanes::LaneSimulator;
+pub use netlist::Netlist;
+pub use signal::Signal;
+#[cfg(feature = "web")]
 use state::State;
+pub use truth_table::{Row, TruthTable, TruthTableError};
+#[cfg(feature = "web")]
 use wasm_bindgen::prelude::*;
+pub use waveform::{Probe, Waveform};
 
+#[cfg(feature = "web")]
 #[macro_export]
 macro_rules! dbg {
     ( $val:expr ) => {{
         match $val {
             tmp => {
-                crate::print(format_args!(
+                $crate::print(format_args!(
                     "[{}:{}] {} = {:#?}",
                     file!(),
                     line!(),
@@ -25,6 +56,7 @@ macro_rules! dbg {
     }};
 }
 
+#[cfg(feature = "web")]
 #[wasm_bindgen]
 pub async fn start() -> Result<(), JsValue> {
     std::panic::set_hook(Box::new(console_error_panic_hook::hook));
@@ -33,13 +65,16 @@ pub async fn start() -> Result<(), JsValue> {
     Ok(())
 }
 
+#[cfg(feature = "web")]
 pub fn print(args: std::fmt::Arguments) {
     let value = JsValue::from(format!("{args}"));
     web_sys::console::log_1(&value)
 }
 
+#[cfg(feature = "web")]
 pub struct PrintOnDrop(Box<dyn std::fmt::Debug>);
 
+#[cfg(feature = "web")]
 impl PrintOnDrop {
     pub fn new(v: impl std::fmt::Debug + 'static) -> Self {
         Self(Box::new(v) as Box<dyn std::fmt::Debug>)
@@ -0,0 +1,391 @@
+use crate::{
+    grid::{Cell, Grid, Input},
+    nets::Nets,
+    signal::Signal,
+};
+use std::collections::HashMap;
+
+/// Where the signal on a gate input comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// Nothing sends a signal into this input.
+    Low,
+    Gate(usize),
+    Net(usize),
+}
+
+/// One output of a cell. Cells with several [`Cell::outputs`] are compiled
+/// into consecutive gates, one per output.
+#[derive(Debug, Clone)]
+pub struct Gate {
+    /// The cell this gate was compiled from, only its kind and direction matter.
+    pub cell: Cell,
+    pub pos: [usize; 2],
+    /// Which of the outputs of the cell this gate computes.
+    pub output: usize,
+    /// Fan-in, in the order [`Cell::inputs`] returns them.
+    pub inputs: Vec<Source>,
+    /// Gates reading the output of this gate directly.
+    pub fanout: Vec<usize>,
+    /// Nets driven by this gate.
+    pub nets: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Net {
+    /// Gates sending their signal into the net.
+    pub drivers: Vec<usize>,
+    /// Gates with an input connected to the net.
+    pub readers: Vec<usize>,
+    pub points: Vec<[usize; 2]>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Node {
+    Gate(usize),
+    Net(usize),
+}
+
+/// A [`Grid`] compiled into explicit gates and nets.
+///
+/// Stepping a netlist gives the same results as [`Grid::simulate`] without
+/// looking at the neighbours of every cell on every tick. Use
+/// [`Netlist::write_to`] to copy the results back into the grid. The outputs
+/// of [`Cell::Memory`] blocks keep their state, the grid sets them after
+/// every tick, so on its own a netlist only matches grids without memory.
+#[derive(Debug, Clone)]
+pub struct Netlist {
+    gates: Vec<Gate>,
+    nets: Vec<Net>,
+    nodes: HashMap<[usize; 2], Node>,
+    state: Vec<bool>,
+    next: Vec<bool>,
+    net_state: Vec<bool>,
+    /// Set when the incremental bookkeeping is out of date, makes the next
+    /// incremental step look at everything.
+    dirty_all: bool,
+    /// Gates whose state changed during the last step or since then.
+    changed: Vec<usize>,
+    changed_nets: Vec<usize>,
+    /// Gates that have to be evaluated on the next incremental step even if
+    /// none of their inputs changed.
+    touched: Vec<usize>,
+    dirty_nets: Vec<usize>,
+    /// Nets whose points were changed with [`Netlist::set_active`], they are
+    /// reported as changed by the next incremental step so they get restored.
+    forced_nets: Vec<usize>,
+    queued: Vec<bool>,
+    net_queued: Vec<bool>,
+    worklist: Vec<(usize, bool)>,
+    /// Gates of [`Cell::Clock`]s, they change without any input changing.
+    clocks: Vec<usize>,
+    tick: u64,
+}
+
+impl Netlist {
+    pub fn compile(grid: &Grid) -> Self {
+        let width = grid.width();
+        let height = grid.height();
+        let point_nets = Nets::build(grid);
+        let mut nodes = HashMap::new();
+        let mut gates = Vec::new();
+        for (pos, &cell) in grid {
+            if cell.is_gate() {
+                nodes.insert(pos, Node::Gate(gates.len()));
+                for output in 0..cell.outputs() {
+                    gates.push(Gate {
+                        cell,
+                        pos,
+                        output,
+                        inputs: Vec::new(),
+                        fanout: Vec::new(),
+                        nets: Vec::new(),
+                    });
+                }
+            } else if let Some(net) = point_nets.of_cell(pos) {
+                nodes.insert(pos, Node::Net(net));
+            }
+        }
+        let mut nets: Vec<Net> = point_nets
+            .iter()
+            .map(|net| Net {
+                drivers: Vec::new(),
+                readers: Vec::new(),
+                points: net.points.clone(),
+            })
+            .collect();
+        for (id, net) in point_nets.iter().enumerate() {
+            for (pos, dir) in &net.drivers {
+                if let Some(&Node::Gate(first)) = nodes.get(pos) {
+                    if let Some(output) = gates[first].cell.output_towards(*dir) {
+                        let gate = first + output;
+                        if !nets[id].drivers.contains(&gate) {
+                            nets[id].drivers.push(gate);
+                            gates[gate].nets.push(id);
+                        }
+                    }
+                }
+            }
+        }
+        for id in 0..gates.len() {
+            let [x, y] = gates[id].pos;
+            let cell = gates[id].cell;
+            let first = id - gates[id].output;
+            for input in cell.inputs(gates[id].output) {
+                let source = match input {
+                    Input::Output(output) => Source::Gate(first + output),
+                    Input::Side(dir) => {
+                        let (ox, oy): (i32, i32) = dir.into();
+                        let nx = x as i64 + ox as i64;
+                        let ny = y as i64 + oy as i64;
+                        if nx < 0 || nx >= width as i64 || ny < 0 || ny >= height as i64 {
+                            Source::Low
+                        } else {
+                            match nodes.get(&[nx as usize, ny as usize]).copied() {
+                                Some(Node::Gate(g)) => {
+                                    match gates[g].cell.output_towards(dir.rev()) {
+                                        Some(output) => Source::Gate(g + output),
+                                        None => Source::Low,
+                                    }
+                                }
+                                Some(Node::Net(net)) => Source::Net(net),
+                                None => Source::Low,
+                            }
+                        }
+                    }
+                };
+                match source {
+                    Source::Low => {}
+                    Source::Gate(g) => {
+                        if !gates[g].fanout.contains(&id) {
+                            gates[g].fanout.push(id);
+                        }
+                    }
+                    Source::Net(net) => {
+                        if !nets[net].readers.contains(&id) {
+                            nets[net].readers.push(id);
+                        }
+                    }
+                }
+                gates[id].inputs.push(source);
+            }
+        }
+        let clocks = (0..gates.len())
+            .filter(|g| matches!(gates[*g].cell, Cell::Clock { .. }))
+            .collect();
+        let state: Vec<bool> = gates.iter().map(|g| g.cell.output(g.output)).collect();
+        let net_state = nets
+            .iter()
+            .map(|net| net.points.iter().any(|p| grid[*p].is_active()))
+            .collect();
+        Self {
+            next: state.clone(),
+            queued: vec![false; gates.len()],
+            net_queued: vec![false; nets.len()],
+            state,
+            gates,
+            nets,
+            nodes,
+            net_state,
+            dirty_all: true,
+            changed: Vec::new(),
+            changed_nets: Vec::new(),
+            touched: Vec::new(),
+            dirty_nets: Vec::new(),
+            forced_nets: Vec::new(),
+            worklist: Vec::new(),
+            clocks,
+            tick: grid.tick(),
+        }
+    }
+    pub fn step(&mut self) {
+        for net in 0..self.nets.len() {
+            self.net_state[net] = self.net_with(net, &self.state);
+        }
+        let mut next = std::mem::take(&mut self.next);
+        for (gate, next) in next.iter_mut().enumerate() {
+            *next = self.eval(gate);
+        }
+        self.next = std::mem::replace(&mut self.state, next);
+        self.dirty_all = true;
+        self.tick += 1;
+    }
+    /// Same as [`Netlist::step`], but only evaluates the gates whose inputs
+    /// changed during the last step or which were changed with
+    /// [`Netlist::set_active`] since then. A circuit which doesn't change
+    /// costs next to nothing.
+    pub fn step_incremental(&mut self) {
+        let all = std::mem::take(&mut self.dirty_all);
+        if all {
+            self.touched.clear();
+            self.touched.extend(0..self.gates.len());
+            self.dirty_nets.clear();
+            self.dirty_nets.extend(0..self.nets.len());
+            self.queued.iter_mut().for_each(|q| *q = false);
+            self.net_queued.iter_mut().for_each(|q| *q = true);
+        }
+        self.worklist.clear();
+        self.touched.extend_from_slice(&self.clocks);
+        for g in std::mem::take(&mut self.touched) {
+            if !std::mem::replace(&mut self.queued[g], true) {
+                self.worklist.push((g, false));
+            }
+        }
+        for &g in &self.changed {
+            for &n in &self.gates[g].nets {
+                if !std::mem::replace(&mut self.net_queued[n], true) {
+                    self.dirty_nets.push(n);
+                }
+            }
+            for &f in &self.gates[g].fanout {
+                if !std::mem::replace(&mut self.queued[f], true) {
+                    self.worklist.push((f, false));
+                }
+            }
+        }
+        self.changed_nets.clear();
+        self.changed_nets.append(&mut self.forced_nets);
+        let mut dirty_nets = std::mem::take(&mut self.dirty_nets);
+        for n in dirty_nets.drain(..) {
+            self.net_queued[n] = false;
+            let active = self.net_with(n, &self.state);
+            if active == self.net_state[n] && !all {
+                continue;
+            }
+            self.net_state[n] = active;
+            self.changed_nets.push(n);
+            for &r in &self.nets[n].readers {
+                if !std::mem::replace(&mut self.queued[r], true) {
+                    self.worklist.push((r, false));
+                }
+            }
+        }
+        self.dirty_nets = dirty_nets;
+        for i in 0..self.worklist.len() {
+            let g = self.worklist[i].0;
+            self.worklist[i].1 = self.eval(g);
+        }
+        self.changed.clear();
+        for &(g, active) in &self.worklist {
+            self.queued[g] = false;
+            if self.state[g] != active {
+                self.state[g] = active;
+                self.changed.push(g);
+            }
+        }
+        self.tick += 1;
+    }
+    fn eval(&self, gate: usize) -> bool {
+        self.eval_with(gate, self.tick + 1, &self.state, &self.net_state)
+    }
+    /// Evaluates a gate with the signals of the gates and nets taken from
+    /// the given slices instead of the state of this netlist. `tick` is the
+    /// tick the new state is for, it only matters for [`Cell::Clock`]s.
+    pub fn eval_with<S: Signal>(&self, gate: usize, tick: u64, state: &[S], net_state: &[S]) -> S {
+        let gate = &self.gates[gate];
+        if let Some(active) = gate.cell.clock(tick) {
+            return S::splat(active);
+        }
+        let mut inputs = [S::LOW; 4];
+        for (input, source) in inputs.iter_mut().zip(&gate.inputs) {
+            *input = match *source {
+                Source::Low => S::LOW,
+                Source::Gate(g) => state[g],
+                Source::Net(n) => net_state[n],
+            };
+        }
+        gate.cell.eval(gate.output, &inputs[..gate.inputs.len()])
+    }
+    /// The signal of a net given the states of all gates.
+    pub fn net_with<S: Signal>(&self, net: usize, state: &[S]) -> S {
+        self.nets[net]
+            .drivers
+            .iter()
+            .fold(S::LOW, |a, g| a | state[*g])
+    }
+    /// Nodes changed by the last incremental step.
+    pub fn changed(&self) -> impl Iterator<Item = Node> + '_ {
+        self.changed
+            .iter()
+            .map(|g| Node::Gate(*g))
+            .chain(self.changed_nets.iter().map(|n| Node::Net(*n)))
+    }
+    /// Overrides the state of a node, like [`Grid::set_active`] does for cells.
+    pub fn set_active(&mut self, node: Node, active: bool) {
+        match node {
+            Node::Gate(g) => {
+                if self.state[g] != active {
+                    self.state[g] = active;
+                    self.changed.push(g);
+                }
+                self.touched.push(g);
+            }
+            // the points of a net are recomputed on every tick anyway
+            Node::Net(n) => {
+                if active != self.net_state[n] {
+                    self.forced_nets.push(n);
+                }
+            }
+        }
+    }
+    /// Number of ticks simulated, including the ones of the grid before it
+    /// was compiled.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+    pub fn gates(&self) -> &[Gate] {
+        &self.gates
+    }
+    pub fn nets(&self) -> &[Net] {
+        &self.nets
+    }
+    /// The gate or net the cell at the given position was compiled into.
+    pub fn node(&self, x: usize, y: usize) -> Option<Node> {
+        self.nodes.get(&[x, y]).copied()
+    }
+    pub fn is_active(&self, node: Node) -> bool {
+        match node {
+            Node::Gate(g) => self.state[g],
+            Node::Net(n) => self.net_state[n],
+        }
+    }
+    /// The state of the cell at the given position, `None` for empty cells.
+    /// Active if any of the external outputs of the cell is.
+    pub fn get(&self, x: usize, y: usize) -> Option<bool> {
+        self.node(x, y).map(|node| match node {
+            Node::Gate(first) => self.gates[first]
+                .cell
+                .external_outputs()
+                .any(|output| self.state[first + output]),
+            node => self.is_active(node),
+        })
+    }
+    /// Copies the state of every gate and net back into the cells of the grid
+    /// it was compiled from.
+    pub fn write_to(&self, grid: &mut Grid) {
+        for (gate, active) in self.gates.iter().zip(&self.state) {
+            grid.set_output(gate.pos[0], gate.pos[1], gate.output, *active);
+        }
+        for (net, active) in self.nets.iter().zip(&self.net_state) {
+            for [x, y] in &net.points {
+                grid.set_active(*x, *y, *active);
+            }
+        }
+    }
+}
+
+#[test]
+fn netlist_matches_grid_simulate() {
+    for seed in 1..20 {
+        let initial = crate::grid::random_grid(16, 12, seed);
+        let mut grid = initial.clone();
+        let mut netlist = Netlist::compile(&grid);
+        for _ in 0..30 {
+            grid.simulate();
+            netlist.step();
+            let mut compiled = initial.clone();
+            netlist.write_to(&mut compiled);
+            assert_eq!(grid, compiled);
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use crate::grid::{Cell, Direction, Grid};
+use std::collections::{hash_map::Entry, HashMap};
+
+/// Connected groups of [`Cell::Point`]s.
+///
+/// All points of a net share one signal, which is active as soon as any cell
+/// next to the net sends a signal into it. The table only depends on where the
+/// points are, so it is built once and reused until the grid is edited.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Nets {
+    /// Net of every point of the grid.
+    of_cell: HashMap<[usize; 2], usize>,
+    nets: Vec<Net>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Net {
+    /// Positions of the points belonging to this net.
+    pub points: Vec<[usize; 2]>,
+    /// Cells next to the net which drive it when they send a signal in the
+    /// given direction.
+    pub drivers: Vec<([usize; 2], Direction)>,
+}
+
+impl Nets {
+    pub fn build(grid: &Grid) -> Self {
+        let width = grid.width();
+        let height = grid.height();
+        let is_point = |[x, y]: [usize; 2]| matches!(grid[[x, y]], Cell::Point { .. });
+        let mut of_cell = HashMap::new();
+        let mut nets = Vec::new();
+        let mut stack = Vec::new();
+        for (start, cell) in grid {
+            if of_cell.contains_key(&start) || !matches!(cell, Cell::Point { .. }) {
+                continue;
+            }
+            let id = nets.len();
+            let mut net = Net::default();
+            of_cell.insert(start, id);
+            stack.push(start);
+            while let Some(pos) = stack.pop() {
+                net.points.push(pos);
+                let (x, y) = (pos[0] as i64, pos[1] as i64);
+                for dir in Direction::all() {
+                    let (ox, oy): (i32, i32) = dir.into();
+                    let (nx, ny) = (x + ox as i64, y + oy as i64);
+                    if nx < 0 || nx >= width as i64 || ny < 0 || ny >= height as i64 {
+                        continue;
+                    }
+                    let n = [nx as usize, ny as usize];
+                    if !is_point(n) {
+                        net.drivers.push((n, dir.rev()));
+                    } else if let Entry::Vacant(entry) = of_cell.entry(n) {
+                        entry.insert(id);
+                        stack.push(n);
+                    }
+                }
+            }
+            nets.push(net);
+        }
+        Self { of_cell, nets }
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &Net> {
+        self.nets.iter()
+    }
+    /// The net the cell at the given position belongs to.
+    pub fn of_cell(&self, pos: [usize; 2]) -> Option<usize> {
+        self.of_cell.get(&pos).copied()
+    }
+}
@@ -0,0 +1,33 @@
+use std::ops::{BitAnd, BitOr, BitXor, Not};
+
+/// Value carried by a wire, either a single `bool` or a `u64` holding one
+/// independent signal per bit for bit parallel simulation.
+pub trait Signal:
+    Copy
+    + Eq
+    + std::fmt::Debug
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + BitXor<Output = Self>
+    + Not<Output = Self>
+{
+    const LOW: Self;
+    const HIGH: Self;
+
+    fn splat(active: bool) -> Self {
+        match active {
+            true => Self::HIGH,
+            false => Self::LOW,
+        }
+    }
+}
+
+impl Signal for bool {
+    const LOW: Self = false;
+    const HIGH: Self = true;
+}
+
+impl Signal for u64 {
+    const LOW: Self = 0;
+    const HIGH: Self = !0;
+}
@@ -1,13 +1,19 @@
 use crate::{
+    analyzer::Analyzer,
     canvas::Canvas,
     event_loop::{Event, EventLoop, Key, MouseButton, Quit},
-    grid::{Cell, Direction, Grid},
+    grid::{
+        Anchor, Cell, Direction, DisplayKind, Grid, Instance, Memory, MemoryKind, PinKind, Settle,
+    },
+    history::History,
     image::Image,
+    truth_table::{pin_names, TruthTable, TruthTableError},
+    waveform::Waveform,
     PrintOnDrop,
 };
 use glam::DVec2;
 use std::collections::HashMap;
-use wasm_bindgen::JsValue;
+use wasm_bindgen::{JsCast, JsValue};
 
 struct KeyState<K> {
     keys: HashMap<K, bool>,
@@ -35,7 +41,7 @@ impl<K: std::hash::Hash + std::cmp::Eq> KeyState<K> {
     }
 }
 
-const CELLS: &'static [Cell] = &[
+const CELLS: &[Cell] = &[
     Cell::And {
         active: false,
         direction: Direction::Up,
@@ -52,12 +58,119 @@ const CELLS: &'static [Cell] = &[
         active: false,
         direction: Direction::Up,
     },
-    Cell::Point {
-        active: true,
-        marked: 0,
+    Cell::Point { active: true },
+    Cell::Or {
+        active: false,
+        direction: Direction::Up,
+    },
+    Cell::Xor {
+        active: false,
+        direction: Direction::Up,
+    },
+    Cell::Nand {
+        active: false,
+        direction: Direction::Up,
+    },
+    Cell::Nor {
+        active: false,
+        direction: Direction::Up,
+    },
+    Cell::Cross {
+        vertical: false,
+        horizontal: false,
+        direction: Direction::Up,
+    },
+    Cell::Corner {
+        active: false,
+        direction: Direction::Up,
+        mirrored: false,
+    },
+    Cell::Delay {
+        direction: Direction::Up,
+        ticks: 1,
+        stages: 0,
+    },
+    Cell::DFlipFlop {
+        active: false,
+        direction: Direction::Up,
+        clock: false,
+    },
+    Cell::SrLatch {
+        active: false,
+        direction: Direction::Up,
+    },
+    Cell::Clock {
+        active: false,
+        direction: Direction::Up,
+        period: 2,
+        phase: 0,
+        high: 1,
+    },
+    Cell::Switch {
+        active: false,
+        direction: Direction::Up,
+    },
+    Cell::Button {
+        active: false,
+        direction: Direction::Up,
+    },
+    Cell::Lamp { active: false },
+    Cell::Display {
+        active: false,
+        kind: DisplayKind::Hex,
+        x: 0,
+        y: 0,
+    },
+    Cell::Display {
+        active: false,
+        kind: DisplayKind::Segments,
+        x: 0,
+        y: 0,
+    },
+    // named when picked from the hotbar
+    Cell::Pin {
+        active: false,
+        direction: Direction::Up,
+        kind: PinKind::In,
+        name: 0,
+    },
+    Cell::Pin {
+        active: false,
+        direction: Direction::Up,
+        kind: PinKind::Out,
+        name: 0,
+    },
+    // filled when picked from the hotbar
+    Cell::Memory {
+        active: false,
+        kind: MemoryKind::Rom,
+        memory: 0,
+        x: 0,
+        y: 0,
+    },
+    Cell::Memory {
+        active: false,
+        kind: MemoryKind::Ram,
+        memory: 0,
+        x: 0,
+        y: 0,
     },
 ];
 
+/// How many ticks [`Key::T`] simulates at most while waiting for the grid to
+/// settle.
+const SETTLE_TICKS: u64 = 10000;
+
+/// Limits of the truth table made with [`Key::I`], which is made at once
+/// while the editor waits.
+const TRUTH_TABLE_INPUTS: usize = 10;
+const TRUTH_TABLE_TICKS: u64 = 1000;
+
+/// Default limits of the history, the `history` and `history_memory` URL
+/// parameters set the number of ticks and the megabytes instead.
+const HISTORY_DEPTH: usize = 10000;
+const HISTORY_MEMORY: usize = 64 << 20;
+
 struct Rect {
     pos: DVec2,
     size: DVec2,
@@ -109,19 +222,58 @@ pub struct State {
     and_image: Image,
     not_image: Image,
     tee_image: Image,
+    or_image: Image,
+    xor_image: Image,
+    nand_image: Image,
+    nor_image: Image,
+    cross_image: Image,
+    corner_image: Image,
+    delay_image: Image,
+    dff_image: Image,
+    sr_latch_image: Image,
+    clock_image: Image,
+    switch_image: Image,
+    button_image: Image,
+    lamp_image: Image,
+    pin_in_image: Image,
+    pin_out_image: Image,
     red_image: Image,
     dark_red_image: Image,
+    display_image: Image,
+    input_image: Image,
     ui_backgroud_image: Image,
     tick: u64,
     cam: CamRect,
     panning: Option<DVec2>,
     grid: Box<Grid>,
     hand: Cell,
+    /// Contents of the memory block in the hand, they only become part of the
+    /// grid when the block is placed.
+    hand_memory: Option<Memory>,
     screen_size: DVec2,
     quit: bool,
     keys: KeyState<Key>,
     time: f64,
     running: bool,
+    /// The [`Cell::Button`] held down with the mouse.
+    pressed: Option<[usize; 2]>,
+    /// First corner of the region that becomes a module, see [`Key::G`].
+    selection: Option<[usize; 2]>,
+    /// The module placed on click instead of the hand.
+    held_module: Option<usize>,
+    /// The result of the last [`Key::T`], shown until the next input.
+    settled: Option<Settle>,
+    /// Past ticks to step back to with [`Key::Left`] or the timeline.
+    history: History,
+    /// Set while the timeline is dragged.
+    scrubbing: bool,
+    /// Signals of the cells probed with [`Key::P`], saved with [`Key::V`].
+    waveform: Waveform,
+    /// Panel showing the waveform, toggled with [`Key::L`].
+    analyzer: Analyzer,
+    /// Truth table of all pins shown with [`Key::I`], made when it was
+    /// opened.
+    truth_table: Option<TruthTable>,
 }
 
 impl State {
@@ -131,8 +283,25 @@ impl State {
         let and_image = Image::load("/assets/and.png").await?;
         let not_image = Image::load("/assets/not.png").await?;
         let tee_image = Image::load("/assets/tee.png").await?;
+        let or_image = Image::load("/assets/or.png").await?;
+        let xor_image = Image::load("/assets/xor.png").await?;
+        let nand_image = Image::load("/assets/nand.png").await?;
+        let nor_image = Image::load("/assets/nor.png").await?;
+        let cross_image = Image::load("/assets/cross.png").await?;
+        let corner_image = Image::load("/assets/corner.png").await?;
+        let delay_image = Image::load("/assets/delay.png").await?;
+        let dff_image = Image::load("/assets/dff.png").await?;
+        let sr_latch_image = Image::load("/assets/sr_latch.png").await?;
+        let clock_image = Image::load("/assets/clock.png").await?;
+        let switch_image = Image::load("/assets/switch.png").await?;
+        let button_image = Image::load("/assets/button.png").await?;
+        let lamp_image = Image::load("/assets/lamp.png").await?;
+        let pin_in_image = Image::load("/assets/pin_in.png").await?;
+        let pin_out_image = Image::load("/assets/pin_out.png").await?;
         let red_image = Image::new(1, 1, &[0xFF, 0, 0, 0xFF]);
         let dark_red_image = Image::new(1, 1, &[0x88, 0, 0, 0xFF]);
+        let display_image = Image::new(1, 1, &[0x22, 0x22, 0x22, 0xFF]);
+        let input_image = Image::new(1, 1, &[0xE0, 0x7E, 0x27, 0xFF]);
         let ui_backgroud_image = Image::new(1, 1, &[0x59, 0x3b, 0x13, 0xFF]);
 
         let mouse_pos = DVec2::new(0.0, 0.0);
@@ -154,35 +323,68 @@ impl State {
             .location()
             .unwrap();
         let search = location.search().unwrap();
-        let grid = web_sys::UrlSearchParams::new_with_str(&search)
-            .unwrap()
+        let params = web_sys::UrlSearchParams::new_with_str(&search).unwrap();
+        let grid = params
             .get("save")
             .and_then(|str| {
                 crate::dbg!(&str);
-                Grid::deserialize(str.as_bytes().into_iter().map(|b| *b - 33)).ok()
+                Grid::from_save_string(&str).ok()
             })
-            .unwrap_or_else(|| Grid::new(10, 10));
+            .unwrap_or_else(Grid::unbounded);
         let grid = Box::new(grid);
+        let limit = |name: &str| params.get(name).and_then(|limit| limit.parse().ok());
+        let mut history = History::new(
+            limit("history").unwrap_or(HISTORY_DEPTH),
+            limit("history_memory").map_or(HISTORY_MEMORY, |megabytes: usize| megabytes << 20),
+        );
+        history.record(&grid);
         Ok(Self {
             canvas,
             cable_image,
             and_image,
             not_image,
             tee_image,
+            or_image,
+            xor_image,
+            nand_image,
+            nor_image,
+            cross_image,
+            corner_image,
+            delay_image,
+            dff_image,
+            sr_latch_image,
+            clock_image,
+            switch_image,
+            button_image,
+            lamp_image,
+            pin_in_image,
+            pin_out_image,
             mouse_pos,
             tick,
             cam,
             panning,
             grid,
             hand: Cell::Empty,
+            hand_memory: None,
             screen_size,
             quit: false,
             red_image,
             dark_red_image,
+            display_image,
+            input_image,
             ui_backgroud_image,
             keys,
             time,
             running,
+            pressed: None,
+            selection: None,
+            held_module: None,
+            settled: None,
+            history,
+            scrubbing: false,
+            waveform: Waveform::new(),
+            analyzer: Analyzer::new(),
+            truth_table: None,
         })
     }
 }
@@ -207,7 +409,7 @@ impl EventLoop for State {
             self.make_active(|_| true)
         }
         if self.running {
-            self.grid.simulate();
+            self.simulate();
         }
         self.tick += 1;
         Quit::No
@@ -222,12 +424,42 @@ impl EventLoop for State {
                     self.screen_size.min_element(),
                 );
             }
-            Event::MouseMove(x, y) => self.mouse_pos = DVec2::new(x as f64, y as f64),
+            Event::MouseMove(x, y) => {
+                self.mouse_pos = DVec2::new(x as f64, y as f64);
+                if self.scrubbing {
+                    self.scrub_to_mouse();
+                }
+            }
             Event::MouseDown(button) => match button {
                 MouseButton::Secondary => {
                     self.panning = Some(self.cam.screen_to_world(self.mouse_pos));
                 }
                 MouseButton::Primary => {
+                    self.settled = None;
+                    if let Some(bounds) = self.analyzer_bounds() {
+                        let tick = self
+                            .analyzer
+                            .tick_at(&self.waveform, bounds, self.mouse_pos);
+                        if let Some(tick) = tick {
+                            self.running = false;
+                            self.history.scrub(&mut self.grid, tick);
+                        }
+                        if tick.is_some()
+                            || self
+                                .analyzer
+                                .probe_at(&self.waveform, bounds, self.mouse_pos)
+                                .is_some()
+                        {
+                            return;
+                        }
+                    }
+                    if let Some((rect, _)) = self.timeline() {
+                        if rect.contains(self.mouse_pos) {
+                            self.scrubbing = true;
+                            self.scrub_to_mouse();
+                            return;
+                        }
+                    }
                     let mut rects = CellRectsIter::new(self.screen_size, 50.0);
                     let clicked_on_hotbar = loop {
                         let (rect, cell) = match rects.next() {
@@ -235,20 +467,48 @@ impl EventLoop for State {
                             None => break false,
                         };
                         if rect.contains(self.mouse_pos) {
-                            self.hand = *cell;
+                            self.hand = match *cell {
+                                Cell::Pin {
+                                    direction, kind, ..
+                                } => web_sys::window()
+                                    .unwrap()
+                                    .prompt_with_message("Name of the pin")
+                                    .ok()
+                                    .flatten()
+                                    .filter(|name| !name.is_empty())
+                                    .and_then(|name| self.grid.pin(&name, kind, direction))
+                                    .unwrap_or(Cell::Empty),
+                                Cell::Memory { kind, .. } => self.new_memory(kind),
+                                cell => cell,
+                            };
+                            self.held_module = None;
                             break true;
                         }
                     };
                     if !clicked_on_hotbar {
-                        let mouse_pos = self.cam.screen_to_world(self.mouse_pos).floor().as_uvec2();
-                        if mouse_pos.x < self.grid.width() as u32
-                            && mouse_pos.y < self.grid.height() as u32
-                        {
+                        if let Some([x, y]) = self.hovered() {
+                            let cell = self.grid[[x, y]];
                             if self.keys[Key::Shift] {
-                                self.grid[[mouse_pos.x as usize, mouse_pos.y as usize]] =
-                                    Cell::Empty;
+                                self.grid.erase(x, y);
+                            } else if let Some(module) = self.held_module {
+                                self.grid.place_module(x, y, module);
+                            } else if let Cell::Switch { active, .. }
+                            | Cell::Pin {
+                                active,
+                                kind: PinKind::In,
+                                ..
+                            } = cell
+                            {
+                                self.grid.set_active(x, y, !active);
+                            } else if let Cell::Button { .. } = cell {
+                                self.grid.set_active(x, y, true);
+                                self.pressed = Some([x, y]);
+                            } else if let (Cell::Memory { kind, .. }, Some(memory)) =
+                                (self.hand, &self.hand_memory)
+                            {
+                                self.grid.place_memory(x, y, kind, memory.clone());
                             } else if self.hand != Cell::Empty {
-                                self.grid[[mouse_pos.x as usize, mouse_pos.y as usize]] = self.hand;
+                                self.grid.place(x, y, self.hand);
                             }
                         }
                     }
@@ -256,6 +516,14 @@ impl EventLoop for State {
                 _ => {}
             },
             Event::MouseUp(button) => match button {
+                MouseButton::Primary => {
+                    self.scrubbing = false;
+                    if let Some([x, y]) = self.pressed.take() {
+                        if let Some(Cell::Button { .. }) = self.grid.get(x, y) {
+                            self.grid.set_active(x, y, false);
+                        }
+                    }
+                }
                 MouseButton::Secondary => {
                     self.panning = None;
                 }
@@ -266,6 +534,26 @@ impl EventLoop for State {
                 _ => {}
             },
             Event::MouseWheel(_, dy) => {
+                if let Some(bounds) = self.analyzer_bounds() {
+                    if self
+                        .analyzer
+                        .probe_at(&self.waveform, bounds, self.mouse_pos)
+                        .is_some()
+                    {
+                        match self.keys[Key::Shift] {
+                            true => self.analyzer.pan(&self.waveform, dy.signum() * 0.1),
+                            false if dy > 0.0 => {
+                                self.analyzer
+                                    .zoom(&self.waveform, bounds, self.mouse_pos, 1.25)
+                            }
+                            false => {
+                                self.analyzer
+                                    .zoom(&self.waveform, bounds, self.mouse_pos, 0.8)
+                            }
+                        }
+                        return;
+                    }
+                }
                 let o = self.cam.screen_to_world(self.mouse_pos);
                 if dy > 0.0 {
                     self.cam.size *= 1.1;
@@ -278,22 +566,22 @@ impl EventLoop for State {
             }
             Event::KeyDown(key) => {
                 self.keys.set(key, true);
+                self.settled = None;
                 match key {
-                    Key::R => {
-                        if self.hand != Cell::Empty {
-                            if let Some(direction) = self.hand.direction_mut() {
-                                *direction = direction.rotate_cw();
-                            }
-                        } else {
-                            let mouse_pos =
-                                self.cam.screen_to_world(self.mouse_pos).floor().as_uvec2();
-                            if mouse_pos.x < self.grid.width() as u32
-                                && mouse_pos.y < self.grid.height() as u32
-                            {
-                                self.grid[[mouse_pos.x as usize, mouse_pos.y as usize]].rotate();
-                            }
-                        }
+                    Key::Up | Key::Down | Key::Left | Key::Right if self.keys[Key::Alt] => {
+                        let offset = match key {
+                            Key::Up => [0, -1],
+                            Key::Down => [0, 1],
+                            Key::Left => [-1, 0],
+                            _ => [1, 0],
+                        };
+                        self.grid.shift(offset);
+                        self.shifted(offset);
                     }
+                    Key::R => self.edit_hand_or_hovered(Cell::rotate),
+                    Key::M => self.edit_hand_or_hovered(Cell::mirror),
+                    Key::Up => self.adjust(1),
+                    Key::Down => self.adjust(-1),
                     Key::E => {
                         if self.hand != Cell::Empty {
                             self.hand.set(!self.hand.is_active())
@@ -301,31 +589,94 @@ impl EventLoop for State {
                             self.make_active(|b| !b);
                         }
                     }
-                    Key::G => {
-                        crate::dbg!(&self.grid.marker);
+                    Key::G => self.select_module(),
+                    Key::O => {
+                        if let Some(instance) = self
+                            .hovered()
+                            .and_then(|[x, y]| self.grid.instance_at(x, y))
+                        {
+                            self.grid.open_instance(instance);
+                        }
+                    }
+                    Key::Escape => {
+                        self.selection = None;
+                        self.held_module = None;
                     }
                     Key::Q => {
-                        if self.hand == Cell::Empty {
-                            let mouse_pos =
-                                self.cam.screen_to_world(self.mouse_pos).floor().as_uvec2();
-                            self.hand = *self
-                                .grid
-                                .get(mouse_pos.x as usize, mouse_pos.y as usize)
-                                .unwrap_or(&Cell::Empty);
-                        } else {
+                        if self.held_module.is_some() {
+                            self.held_module = None;
+                        } else if self.hand != Cell::Empty {
                             self.hand = Cell::Empty;
+                        } else if let Some(instance) = self.hovered_instance() {
+                            self.held_module = Some(instance.module);
+                        } else if let Some([x, y]) = self.hovered() {
+                            self.hand = self.grid[[x, y]];
+                            self.hand_memory = self.grid.memory(&self.hand).cloned();
+                        }
+                    }
+                    Key::Right if self.keys[Key::Shift] => {
+                        self.history.step_forward(&mut self.grid, 1);
+                    }
+                    Key::Right => self.simulate(),
+                    Key::Left => {
+                        self.running = false;
+                        self.history.step_back(&mut self.grid, 1);
+                    }
+                    Key::T => {
+                        self.settled = Some(self.grid.settle(SETTLE_TICKS));
+                        self.history.record(&self.grid);
+                        self.waveform.sample(&self.grid);
+                    }
+                    Key::P => self.toggle_probe(),
+                    Key::V => self.download("circuit.vcd", &self.waveform.vcd()),
+                    Key::I if self.keys[Key::Shift] => {
+                        if let Some(table) = self.make_truth_table() {
+                            self.download("truth_table.csv", &table.csv());
                         }
                     }
-                    Key::Right => {
-                        self.grid.simulate();
+                    Key::I if self.keys[Key::Control] => {
+                        if let Some(table) = self.make_truth_table() {
+                            let markdown = table.markdown();
+                            wasm_bindgen_futures::spawn_local(async move {
+                                wasm_bindgen_futures::JsFuture::from(
+                                    web_sys::window()
+                                        .unwrap()
+                                        .navigator()
+                                        .clipboard()
+                                        .unwrap()
+                                        .write_text(&markdown),
+                                )
+                                .await
+                                .unwrap();
+                            });
+                        }
+                    }
+                    Key::I => {
+                        self.truth_table = match self.truth_table {
+                            Some(_) => None,
+                            None => self.make_truth_table(),
+                        };
                     }
+                    Key::C => {
+                        let offset = self.grid.crop();
+                        self.moved(offset);
+                    }
+                    Key::A => {
+                        let side = self.nearest_edge();
+                        let offset = match self.keys[Key::Shift] {
+                            true => self.grid.remove_lines(side, 1),
+                            false => self.grid.add_lines(side, 1),
+                        };
+                        self.moved(offset);
+                    }
+                    Key::Z => self.resize(),
                     Key::Space => {
                         self.running = !self.running;
+                        self.analyzer.follow();
                     }
+                    Key::L => self.analyzer.open = !self.analyzer.open,
                     Key::S => {
-                        let save =
-                            String::from_utf8(self.grid.serialize().map(|b| b + 33).collect())
-                                .unwrap();
+                        let save = self.grid.to_save_string();
                         let params = web_sys::UrlSearchParams::new().unwrap();
                         params.set("save", &save);
                         web_sys::window()
@@ -367,30 +718,136 @@ impl EventLoop for State {
         self.canvas.set_fill_style(&black);
         self.canvas.fill_rect(0.0, 0.0, w, h);
 
-        for x in 0..10 + 1 {
-            let x = x as f64;
-            let p1 = self.cam.world_to_screen(DVec2::new(x, 0.0));
-            let p2 = self.cam.world_to_screen(DVec2::new(x, 10.0));
-            self.canvas.draw_line("green", p1, p2);
-        }
-        for y in 0..10 + 1 {
-            let y = y as f64;
-            let p1 = self.cam.world_to_screen(DVec2::new(0.0, y));
-            let p2 = self.cam.world_to_screen(DVec2::new(10.0, y));
-            self.canvas.draw_line("green", p1, p2);
-        }
+        // only the part of the grid in view is drawn
+        let corners = [DVec2::ZERO, DVec2::new(w, h)].map(|p| self.cam.screen_to_world(p));
+        let size = DVec2::new(self.grid.width() as f64, self.grid.height() as f64);
+        let min = corners[0].min(corners[1]).floor().clamp(DVec2::ZERO, size);
+        let max = corners[0].max(corners[1]).ceil().clamp(DVec2::ZERO, size);
+        let visible = |x: usize, y: usize| {
+            let pos = DVec2::new(x as f64, y as f64);
+            pos.cmpge(min - 1.0).all() && pos.cmplt(max).all()
+        };
 
         let block_size = self.cam.screen / self.cam.size;
+        // lines closer than a few pixels would just fill the screen
+        if block_size.min_element() >= 4.0 {
+            for x in min.x as usize..=max.x as usize {
+                let p1 = self.cam.world_to_screen(DVec2::new(x as f64, min.y));
+                let p2 = self.cam.world_to_screen(DVec2::new(x as f64, max.y));
+                self.canvas.draw_line("green", p1, p2);
+            }
+            for y in min.y as usize..=max.y as usize {
+                let p1 = self.cam.world_to_screen(DVec2::new(min.x, y as f64));
+                let p2 = self.cam.world_to_screen(DVec2::new(max.x, y as f64));
+                self.canvas.draw_line("green", p1, p2);
+            }
+        }
+
         for ([x, y], cell) in &*self.grid {
+            if *cell == Cell::Empty || !visible(x, y) {
+                continue;
+            }
             let pos = DVec2::new(x as f64, y as f64);
             let pos = self.cam.world_to_screen(pos);
-            if *cell != Cell::Empty {
-                if cell.is_active() {
-                    self.canvas
-                        .draw_image(&self.red_image, pos, block_size, 0.0, 1.0);
+            self.draw_signal(cell, pos, block_size, 1.0);
+            self.draw_cell(cell, pos, block_size, 1.0);
+            if let Some(name) = self.grid.pin_name(cell) {
+                self.draw_label(name, pos, block_size, 1.0);
+            }
+        }
+        // displays cover several cells, draw their digits over all of them
+        for ([x, y], _) in &*self.grid {
+            if let Some((kind, inputs)) = self.grid.display(x, y) {
+                let pos = self.cam.world_to_screen(DVec2::new(x as f64, y as f64));
+                let [width, height] = kind.size();
+                let size = block_size * DVec2::new(width as f64, height as f64);
+                self.draw_digit(kind.segments(inputs), pos, size, 1.0);
+            }
+        }
+
+        for probe in self.waveform.probes() {
+            let [x, y] = probe.pos;
+            let pos = self.cam.world_to_screen(DVec2::new(x as f64, y as f64));
+            let size = block_size * 0.3;
+            self.canvas.set_fill_style(&JsValue::from("cyan"));
+            self.canvas
+                .fill_rect(pos.x + block_size.x - size.x, pos.y, size.x, size.y);
+        }
+
+        // instances are drawn as boxes showing only their pins
+        for instance in self.grid.instances() {
+            let module = &self.grid.modules()[instance.module];
+            let [x, y] = instance.pos;
+            let pos = self.cam.world_to_screen(DVec2::new(x as f64, y as f64));
+            self.draw_module(instance.module, pos, block_size, 1.0);
+            for ([dx, dy], cell) in &module.grid {
+                if let Some(name) = module.grid.pin_name(cell) {
+                    let pos = pos + block_size * DVec2::new(dx as f64, dy as f64);
+                    self.draw_signal(&self.grid[[x + dx, y + dy]], pos, block_size, 1.0);
+                    self.draw_cell(cell, pos, block_size, 1.0);
+                    self.draw_label(name, pos, block_size, 1.0);
                 }
             }
-            self.draw_cell(cell, pos, block_size, 1.0);
+        }
+        if let (Some([x, y]), Some([hx, hy])) = (self.selection, self.hovered()) {
+            let min = DVec2::new(x.min(hx) as f64, y.min(hy) as f64);
+            let max = DVec2::new(x.max(hx) as f64 + 1.0, y.max(hy) as f64 + 1.0);
+            self.draw_outline(min, max);
+        }
+        if let Some((rect, [oldest, newest])) = self.timeline() {
+            let tick = self.grid.tick();
+            let done = (tick.clamp(oldest, newest) - oldest) as f64 / (newest - oldest) as f64;
+            self.canvas.set_fill_style(&JsValue::from("#333333"));
+            self.canvas
+                .fill_rect(rect.pos.x, rect.pos.y, rect.size.x, rect.size.y);
+            self.canvas.set_fill_style(&JsValue::from("#888888"));
+            self.canvas
+                .fill_rect(rect.pos.x, rect.pos.y, rect.size.x * done, rect.size.y);
+            self.canvas.set_fill_style(&JsValue::from("white"));
+            self.canvas.set_font("12px monospace");
+            self.canvas.set_text_align("left");
+            self.canvas.set_text_baseline("bottom");
+            let text = format!("tick {tick} of {oldest}..{newest}");
+            self.canvas
+                .fill_text(&text, rect.pos.x, rect.pos.y - 2.0)
+                .unwrap();
+        }
+        if let Some(bounds) = self.analyzer_bounds() {
+            let tick = self.grid.tick();
+            self.analyzer
+                .draw(&self.canvas, &self.waveform, bounds, self.mouse_pos, tick);
+            if let Some(probe) = self
+                .analyzer
+                .probe_at(&self.waveform, bounds, self.mouse_pos)
+            {
+                let [x, y] = self.waveform.probes()[probe].pos;
+                let min = DVec2::new(x as f64, y as f64);
+                self.draw_outline(min, min + DVec2::ONE);
+            }
+        }
+        if let Some(settled) = &self.settled {
+            let status = match settled {
+                Settle::Stable { ticks } => format!("stable after {ticks} ticks"),
+                Settle::Oscillating { period, .. } => {
+                    format!("oscillates with a period of {period} ticks")
+                }
+                Settle::Unsettled => format!("not settled after {SETTLE_TICKS} ticks"),
+            };
+            if let Settle::Oscillating { cells, .. } = settled {
+                for [x, y] in cells {
+                    let min = DVec2::new(*x as f64, *y as f64);
+                    self.draw_outline(min, min + DVec2::ONE);
+                }
+            }
+            self.canvas.set_fill_style(&JsValue::from("white"));
+            self.canvas.set_font("16px monospace");
+            self.canvas.set_text_align("left");
+            self.canvas.set_text_baseline("top");
+            self.canvas.fill_text(&status, 10.0, 10.0).unwrap();
+        }
+
+        if let Some(table) = &self.truth_table {
+            self.draw_truth_table(table);
         }
 
         let red = JsValue::from("red");
@@ -400,11 +857,26 @@ impl EventLoop for State {
         let pos = self
             .cam
             .world_to_screen(self.cam.screen_to_world(self.mouse_pos).floor());
-        if self.hand.is_active() {
+        self.draw_signal(&self.hand, pos, block_size, 0.5);
+        self.draw_cell(&self.hand, pos, block_size, 1.0);
+        if let Some(name) = self.grid.pin_name(&self.hand) {
+            self.draw_label(name, pos, block_size, 1.0);
+        }
+        if let Cell::Display { kind, .. } = self.hand {
+            let [width, height] = kind.size();
+            let size = block_size * DVec2::new(width as f64, height as f64);
+            self.draw_digit(0, pos, size, 0.5);
+        }
+        if let Some(module) = self.held_module {
+            self.draw_module(module, pos, block_size, 0.5);
+        }
+        if let (Cell::Memory { kind, .. }, Some(memory)) = (self.hand, &self.hand_memory) {
+            let [width, height] = memory.size(kind);
+            let size = block_size * DVec2::new(width as f64, height as f64);
             self.canvas
-                .draw_image(&self.red_image, pos, block_size, 0.0, 0.5);
+                .draw_image(&self.display_image, pos, size, 0.0, 0.5);
         }
-        self.draw_cell(&self.hand, pos, block_size, 1.0);
+        self.draw_memory_inspector();
         let start = DVec2::new(
             self.screen_size.x / 2.0 - CELLS.len() as f64 * 50.0 / 2.0,
             self.screen_size.y - 50.0,
@@ -416,14 +888,90 @@ impl EventLoop for State {
             0.0,
             1.0,
         );
-        for (i, cell) in CELLS.into_iter().enumerate() {
+        for (i, cell) in CELLS.iter().enumerate() {
             let pos = start + DVec2::new(i as f64 * 50.0, 0.0);
             self.draw_cell(cell, pos, DVec2::new(50.0, 50.0), 1.0);
+            if let Cell::Display { kind, .. } = cell {
+                let label = match kind {
+                    DisplayKind::Hex => "hex",
+                    DisplayKind::Segments => "7",
+                };
+                self.draw_digit(0, pos, DVec2::new(50.0, 50.0), 1.0);
+                self.draw_label(label, pos, DVec2::new(50.0, 50.0), 1.0);
+            }
         }
     }
 }
 
 impl State {
+    /// Fills the channels of the active signals of a cell with red, the
+    /// sprite drawn on top lets them show through.
+    fn draw_signal(&self, cell: &Cell, pos: DVec2, size: DVec2, alpha: f64) {
+        match cell {
+            Cell::Empty => {}
+            Cell::Cross {
+                vertical,
+                horizontal,
+                direction,
+            } => {
+                // the channels are 4 of the 32 pixels wide, centered
+                let width = size * 4.0 / 32.0;
+                let offset = size * 14.0 / 32.0;
+                if *vertical {
+                    self.canvas.draw_image(
+                        &self.red_image,
+                        pos + DVec2::new(offset.x, 0.0),
+                        DVec2::new(width.x, size.y),
+                        direction.angle(),
+                        alpha,
+                    );
+                }
+                if *horizontal {
+                    self.canvas.draw_image(
+                        &self.red_image,
+                        pos + DVec2::new(0.0, offset.y),
+                        DVec2::new(size.x, width.y),
+                        direction.angle(),
+                        alpha,
+                    );
+                }
+            }
+            // displays show their signals with their digit, memories with
+            // the markers of their inputs and outputs
+            Cell::Display { .. } | Cell::Memory { .. } => {}
+            _ => {
+                if cell.is_active() {
+                    self.canvas
+                        .draw_image(&self.red_image, pos, size, 0.0, alpha);
+                }
+            }
+        }
+    }
+    /// Draws the segments a to g of a digit filling the given rectangle, lit
+    /// ones red.
+    fn draw_digit(&self, segments: u8, pos: DVec2, size: DVec2, alpha: f64) {
+        let t = size.min_element() * 0.12;
+        let (w, h) = (size.x, size.y);
+        let horizontal = DVec2::new(w - 4.0 * t, t);
+        let vertical = DVec2::new(t, (h - 5.0 * t) / 2.0);
+        let rects = [
+            (DVec2::new(2.0 * t, t), horizontal),
+            (DVec2::new(w - 2.0 * t, 2.0 * t), vertical),
+            (DVec2::new(w - 2.0 * t, (h + t) / 2.0), vertical),
+            (DVec2::new(2.0 * t, h - 2.0 * t), horizontal),
+            (DVec2::new(t, (h + t) / 2.0), vertical),
+            (DVec2::new(t, 2.0 * t), vertical),
+            (DVec2::new(2.0 * t, (h - t) / 2.0), horizontal),
+        ];
+        for (i, (offset, rect)) in rects.into_iter().enumerate() {
+            let image = match segments >> i & 1 == 1 {
+                true => &self.red_image,
+                false => &self.dark_red_image,
+            };
+            self.canvas
+                .draw_image(image, pos + offset, rect, 0.0, alpha);
+        }
+    }
     fn draw_cell(&self, cell: &Cell, pos: DVec2, size: DVec2, alpha: f64) {
         match cell {
             Cell::Empty => {}
@@ -443,6 +991,126 @@ impl State {
                 self.canvas
                     .draw_image(&self.tee_image, pos, size, direction.angle(), alpha)
             }
+            Cell::Or { direction, .. } => {
+                self.canvas
+                    .draw_image(&self.or_image, pos, size, direction.angle(), alpha)
+            }
+            Cell::Xor { direction, .. } => {
+                self.canvas
+                    .draw_image(&self.xor_image, pos, size, direction.angle(), alpha)
+            }
+            Cell::Nand { direction, .. } => {
+                self.canvas
+                    .draw_image(&self.nand_image, pos, size, direction.angle(), alpha)
+            }
+            Cell::Nor { direction, .. } => {
+                self.canvas
+                    .draw_image(&self.nor_image, pos, size, direction.angle(), alpha)
+            }
+            Cell::Cross { direction, .. } => {
+                self.canvas
+                    .draw_image(&self.cross_image, pos, size, direction.angle(), alpha)
+            }
+            Cell::Corner {
+                direction,
+                mirrored,
+                ..
+            } => self.canvas.draw_image_flipped(
+                &self.corner_image,
+                pos,
+                size,
+                direction.angle(),
+                alpha,
+                *mirrored,
+            ),
+            Cell::DFlipFlop { direction, .. } => {
+                self.canvas
+                    .draw_image(&self.dff_image, pos, size, direction.angle(), alpha)
+            }
+            Cell::Pin {
+                direction, kind, ..
+            } => {
+                let image = match kind {
+                    PinKind::In => &self.pin_in_image,
+                    PinKind::Out => &self.pin_out_image,
+                };
+                self.canvas
+                    .draw_image(image, pos, size, direction.angle(), alpha)
+            }
+            Cell::Lamp { .. } => self
+                .canvas
+                .draw_image(&self.lamp_image, pos, size, 0.0, alpha),
+            Cell::Display { active, x, .. } => {
+                self.canvas
+                    .draw_image(&self.display_image, pos, size, 0.0, alpha);
+                if *x == 0 {
+                    // marks the input
+                    let image = match active {
+                        true => &self.red_image,
+                        false => &self.input_image,
+                    };
+                    let marker = size * DVec2::new(0.1, 0.2);
+                    let offset = DVec2::new(0.0, (size.y - marker.y) / 2.0);
+                    self.canvas
+                        .draw_image(image, pos + offset, marker, 0.0, alpha);
+                }
+            }
+            Cell::Memory {
+                active, kind, x, y, ..
+            } => {
+                self.canvas
+                    .draw_image(&self.display_image, pos, size, 0.0, alpha);
+                let image = match (active, x) {
+                    (true, _) => &self.red_image,
+                    (false, 0) => &self.input_image,
+                    (false, _) => &self.dark_red_image,
+                };
+                let marker = size * DVec2::new(0.1, 0.2);
+                let offset = DVec2::new(*x as f64 * (size.x - marker.x), (size.y - marker.y) / 2.0);
+                self.canvas
+                    .draw_image(image, pos + offset, marker, 0.0, alpha);
+                if [*x, *y] == [0, 0] {
+                    let label = match kind {
+                        MemoryKind::Rom => "ROM",
+                        MemoryKind::Ram => "RAM",
+                    };
+                    self.draw_label(label, pos, size, alpha);
+                }
+            }
+            Cell::Switch { direction, .. } => {
+                self.canvas
+                    .draw_image(&self.switch_image, pos, size, direction.angle(), alpha)
+            }
+            Cell::Button { direction, .. } => {
+                self.canvas
+                    .draw_image(&self.button_image, pos, size, direction.angle(), alpha)
+            }
+            Cell::SrLatch { direction, .. } => {
+                self.canvas
+                    .draw_image(&self.sr_latch_image, pos, size, direction.angle(), alpha)
+            }
+            Cell::Delay {
+                direction, ticks, ..
+            } => {
+                self.canvas
+                    .draw_image(&self.delay_image, pos, size, direction.angle(), alpha);
+                self.draw_label(&ticks.to_string(), pos, size, alpha);
+            }
+            Cell::Clock {
+                direction,
+                period,
+                phase,
+                high,
+                ..
+            } => {
+                self.canvas
+                    .draw_image(&self.clock_image, pos, size, direction.angle(), alpha);
+                let label = match phase {
+                    0 => format!("{high}/{period}"),
+                    _ => format!("{high}/{period}+{phase}"),
+                };
+                self.draw_label(&label, pos, size, alpha);
+            }
             Cell::Point { active, .. } => match active {
                 true => self.canvas.draw_image(&self.red_image, pos, size, 0.0, 1.0),
                 false => self
@@ -451,15 +1119,430 @@ impl State {
             },
         };
     }
+    /// Draws the parameters of a cell into its top right corner.
+    fn draw_label(&self, label: &str, pos: DVec2, size: DVec2, alpha: f64) {
+        self.canvas.set_global_alpha(alpha);
+        self.canvas.set_fill_style(&JsValue::from("black"));
+        self.canvas
+            .set_font(&format!("bold {}px monospace", (size.y * 0.25).round()));
+        self.canvas.set_text_align("right");
+        self.canvas.set_text_baseline("top");
+        self.canvas
+            .fill_text(label, pos.x + size.x * 0.95, pos.y + size.y * 0.05)
+            .unwrap();
+        self.canvas.set_global_alpha(1.0);
+    }
+    /// Outlines a rectangle given in world coordinates.
+    fn draw_outline(&self, min: DVec2, max: DVec2) {
+        let corners = [min, DVec2::new(max.x, min.y), max, DVec2::new(min.x, max.y)];
+        for i in 0..4 {
+            self.canvas.draw_line(
+                "yellow",
+                self.cam.world_to_screen(corners[i]),
+                self.cam.world_to_screen(corners[(i + 1) % 4]),
+            );
+        }
+    }
+    /// Asks for the contents of a new ROM or the size of a new RAM.
+    fn new_memory(&mut self, kind: MemoryKind) -> Cell {
+        let window = web_sys::window().unwrap();
+        self.hand_memory = match kind {
+            MemoryKind::Rom => window
+                .prompt_with_message("Contents of the ROM as hexadecimal words")
+                .ok()
+                .flatten()
+                .and_then(|text| Memory::parse_hex(&text))
+                .filter(|words| !words.is_empty())
+                .map(|words| Memory::fitting(&words)),
+            MemoryKind::Ram => window
+                .prompt_with_message_and_default("Address and data bits of the RAM", "4,4")
+                .ok()
+                .flatten()
+                .and_then(|text| {
+                    let (address, data) = text.split_once(',')?;
+                    Some((address.trim().parse().ok()?, data.trim().parse().ok()?))
+                })
+                .map(|(address_bits, data_bits)| Memory::new(address_bits, data_bits, &[])),
+        };
+        match self.hand_memory {
+            Some(_) => Cell::Memory {
+                active: false,
+                kind,
+                memory: 0,
+                x: 0,
+                y: 0,
+            },
+            None => Cell::Empty,
+        }
+    }
+    /// Lists the contents of the memory under the mouse next to it, the word
+    /// at the current address is marked.
+    fn draw_memory_inspector(&self) {
+        let Some([x, y]) = self.hovered() else {
+            return;
+        };
+        let cell = self.grid[[x, y]];
+        let (Cell::Memory { x: dx, y: dy, .. }, Some(memory)) = (cell, self.grid.memory(&cell))
+        else {
+            return;
+        };
+        let address = self.grid.memory_address(x - dx as usize, y - dy as usize);
+        let lines: Vec<String> = memory
+            .words()
+            .chunks(8)
+            .enumerate()
+            .map(|(line, words)| {
+                let words: String = words
+                    .iter()
+                    .enumerate()
+                    .map(|(i, word)| match Some(line * 8 + i) == address {
+                        true => format!(">{word:02x}"),
+                        false => format!(" {word:02x}"),
+                    })
+                    .collect();
+                format!("{:02x}:{words}", line * 8)
+            })
+            .collect();
+        let pos = self.mouse_pos + DVec2::new(20.0, 20.0);
+        let size = DVec2::new(230.0, lines.len() as f64 * 16.0 + 8.0);
+        self.canvas
+            .draw_image(&self.ui_backgroud_image, pos, size, 0.0, 0.9);
+        self.canvas.set_fill_style(&JsValue::from("white"));
+        self.canvas.set_font("14px monospace");
+        self.canvas.set_text_align("left");
+        self.canvas.set_text_baseline("top");
+        for (i, line) in lines.iter().enumerate() {
+            self.canvas
+                .fill_text(line, pos.x + 6.0, pos.y + 4.0 + i as f64 * 16.0)
+                .unwrap();
+        }
+    }
+    /// Draws the box of an instance of a module with its name in the top
+    /// right corner.
+    fn draw_module(&self, module: usize, pos: DVec2, block_size: DVec2, alpha: f64) {
+        let [width, height] = self.grid.module_size(module);
+        let size = block_size * DVec2::new(width as f64, height as f64);
+        self.canvas
+            .draw_image(&self.ui_backgroud_image, pos, size, 0.0, alpha);
+        let name = &self.grid.modules()[module].name;
+        let label_pos = pos + DVec2::new(size.x - block_size.x, 0.0);
+        self.draw_label(name, label_pos, block_size, alpha);
+    }
+    /// The cell under the mouse.
+    fn hovered(&self) -> Option<[usize; 2]> {
+        let mouse_pos = self.cam.screen_to_world(self.mouse_pos).floor();
+        let size = DVec2::new(self.grid.width() as f64, self.grid.height() as f64);
+        (mouse_pos.cmpge(DVec2::ZERO).all() && mouse_pos.cmplt(size).all())
+            .then_some([mouse_pos.x as usize, mouse_pos.y as usize])
+    }
+    fn hovered_instance(&self) -> Option<Instance> {
+        let [x, y] = self.hovered()?;
+        let instance = self.grid.instance_at(x, y)?;
+        Some(self.grid.instances()[instance])
+    }
+    /// The first press marks a corner of the region, the second one the
+    /// opposite corner and asks for the name of the module. The region is
+    /// replaced with an instance of the module, defining a module with an
+    /// existing name updates its instances.
+    fn select_module(&mut self) {
+        let Some([hx, hy]) = self.hovered() else {
+            return;
+        };
+        let Some([x, y]) = self.selection.take() else {
+            self.selection = Some([hx, hy]);
+            return;
+        };
+        let Some(name) = web_sys::window()
+            .unwrap()
+            .prompt_with_message("Name of the module")
+            .ok()
+            .flatten()
+            .filter(|name| !name.is_empty())
+        else {
+            return;
+        };
+        let pos = [x.min(hx), y.min(hy)];
+        let size = [x.abs_diff(hx) + 1, y.abs_diff(hy) + 1];
+        if let Some(module) = self.grid.define_module(&name, pos, size) {
+            self.grid.place_module(pos[0], pos[1], module);
+            self.held_module = Some(module);
+        }
+    }
+    /// Simulates one tick and records it.
+    fn simulate(&mut self) {
+        self.grid.simulate_incremental();
+        self.history.record(&self.grid);
+        self.waveform.sample(&self.grid);
+    }
+    /// Probes the cell under the mouse, asking for the name of the probe, or
+    /// removes its probe.
+    fn toggle_probe(&mut self) {
+        let Some([x, y]) = self.hovered() else {
+            return;
+        };
+        if self.waveform.remove_probe([x, y]).is_some() {
+            return;
+        }
+        let default = match self.grid.pin_name(&self.grid[[x, y]]) {
+            Some(name) => name.to_string(),
+            None => format!("x{x}_y{y}"),
+        };
+        if let Some(name) = web_sys::window()
+            .unwrap()
+            .prompt_with_message_and_default("Name of the probe", &default)
+            .ok()
+            .flatten()
+            .filter(|name| !name.is_empty())
+        {
+            self.waveform.add_probe(&name, [x, y]);
+            self.waveform.sample(&self.grid);
+        }
+    }
+    /// Offers the text as a file, like the recorded signals of the probes as
+    /// a VCD file.
+    fn download(&self, name: &str, text: &str) {
+        let document = web_sys::window().unwrap().document().unwrap();
+        let link: web_sys::HtmlElement = document.create_element("a").unwrap().dyn_into().unwrap();
+        let text = String::from(js_sys::encode_uri_component(text));
+        link.set_attribute("href", &format!("data:text/plain;charset=utf-8,{text}"))
+            .unwrap();
+        link.set_attribute("download", name).unwrap();
+        link.click();
+    }
+    /// The truth table of all pins of the grid, tells why if there is none.
+    fn make_truth_table(&self) -> Option<TruthTable> {
+        let inputs = pin_names(&self.grid, PinKind::In).len();
+        let table = match inputs > TRUTH_TABLE_INPUTS {
+            true => Err(TruthTableError::TooManyInputs(inputs)),
+            false => TruthTable::of_pins(&self.grid, TRUTH_TABLE_TICKS),
+        };
+        let message = match table {
+            Ok(table) if table.outputs.is_empty() => String::from("There are no output pins"),
+            Ok(table) => return Some(table),
+            Err(TruthTableError::TooManyInputs(count)) => format!(
+                "{count} input pins are too many, at most {TRUTH_TABLE_INPUTS} are supported"
+            ),
+            Err(error) => format!("{error:?}"),
+        };
+        web_sys::window()
+            .unwrap()
+            .alert_with_message(&message)
+            .unwrap();
+        None
+    }
+    /// Lists the rows of the truth table below the status line, as many as fit
+    /// on the screen.
+    fn draw_truth_table(&self, table: &TruthTable) {
+        let line = |values: Vec<String>| {
+            let (inputs, outputs) = values.split_at(table.inputs.len());
+            format!("{} | {}", inputs.join(" "), outputs.join(" "))
+        };
+        let width = |name: &String| name.chars().count().max(1);
+        let header = line(
+            table
+                .inputs
+                .iter()
+                .chain(&table.outputs)
+                .map(|name| name.to_string())
+                .collect(),
+        );
+        let value = |(name, value): (&String, Option<bool>)| {
+            let value = match value {
+                None => String::from("x"),
+                Some(active) => (active as u8).to_string(),
+            };
+            format!("{value:>width$}", width = width(name))
+        };
+        let pos = DVec2::new(10.0, 34.0);
+        // the header, the rows and a line about the hidden rows
+        let fitting = ((self.screen_size.y - 100.0 - pos.y) / 16.0).max(3.0) as usize;
+        let shown = match table.rows.len() + 1 > fitting {
+            true => fitting - 2,
+            false => table.rows.len(),
+        };
+        let mut lines = vec![header];
+        for row in &table.rows[..shown] {
+            let inputs = table.inputs.iter().zip(row.inputs.iter().map(|a| Some(*a)));
+            let outputs = table.outputs.iter().zip(row.outputs.iter().copied());
+            lines.push(line(inputs.chain(outputs).map(value).collect()));
+        }
+        if shown < table.rows.len() {
+            lines.push(format!("... {} more rows", table.rows.len() - shown));
+        }
+        let unsettled = table.unsettled().count();
+        let columns = lines.iter().map(|line| line.chars().count()).max();
+        let size = DVec2::new(
+            columns.unwrap_or(0).max(30) as f64 * 8.5 + 12.0,
+            (lines.len() + (unsettled > 0) as usize) as f64 * 16.0 + 8.0,
+        );
+        self.canvas
+            .draw_image(&self.ui_backgroud_image, pos, size, 0.0, 0.9);
+        self.canvas.set_fill_style(&JsValue::from("white"));
+        self.canvas.set_font("14px monospace");
+        self.canvas.set_text_align("left");
+        self.canvas.set_text_baseline("top");
+        for (i, line) in lines.iter().enumerate() {
+            self.canvas
+                .fill_text(line, pos.x + 6.0, pos.y + 4.0 + i as f64 * 16.0)
+                .unwrap();
+        }
+        if unsettled > 0 {
+            self.canvas.set_fill_style(&JsValue::from("red"));
+            let note = format!("{unsettled} rows with outputs that never settle (x)");
+            self.canvas
+                .fill_text(&note, pos.x + 6.0, pos.y + 4.0 + lines.len() as f64 * 16.0)
+                .unwrap();
+        }
+    }
+    /// The bar above the hotbar showing the recorded ticks, if there is more
+    /// than one.
+    fn timeline(&self) -> Option<(Rect, [u64; 2])> {
+        let [oldest, newest] = self.history.ticks()?;
+        let rect = Rect {
+            pos: DVec2::new(10.0, self.screen_size.y - 70.0),
+            size: DVec2::new(self.screen_size.x - 20.0, 10.0),
+        };
+        (newest > oldest).then_some((rect, [oldest, newest]))
+    }
+    /// The logic analyzer sits above the timeline.
+    fn analyzer_bounds(&self) -> Option<[DVec2; 2]> {
+        let bottom = self.screen_size.y - 90.0;
+        self.analyzer
+            .bounds(&self.waveform, self.screen_size, bottom)
+    }
+    /// Goes to the recorded tick under the mouse on the timeline.
+    fn scrub_to_mouse(&mut self) {
+        let Some((rect, [oldest, newest])) = self.timeline() else {
+            return;
+        };
+        let along = ((self.mouse_pos.x - rect.pos.x) / rect.size.x).clamp(0.0, 1.0);
+        let tick = oldest + ((newest - oldest) as f64 * along).round() as u64;
+        self.running = false;
+        self.history.scrub(&mut self.grid, tick);
+    }
+    /// Asks for the new size of the grid, the contents stay at the top left
+    /// or with shift held in the middle.
+    fn resize(&mut self) {
+        let size = match self.grid.is_unbounded() {
+            true => String::new(),
+            false => format!("{},{}", self.grid.width(), self.grid.height()),
+        };
+        let Some(input) = web_sys::window()
+            .unwrap()
+            .prompt_with_message_and_default(
+                "Width and height of the grid, empty for no limit",
+                &size,
+            )
+            .ok()
+            .flatten()
+        else {
+            return;
+        };
+        let size = match input.split_once(',') {
+            None if input.trim().is_empty() => [Grid::UNBOUNDED; 2],
+            Some((width, height)) => match (width.trim().parse(), height.trim().parse()) {
+                (Ok(width), Ok(height)) => [width, height],
+                _ => return,
+            },
+            None => return,
+        };
+        let anchor = match self.keys[Key::Shift] {
+            true => Anchor::Middle,
+            false => Anchor::Start,
+        };
+        let offset = self.grid.resize(size, [anchor; 2]);
+        self.moved(offset);
+    }
+    /// Keeps the camera on the same cells after the contents of the grid were
+    /// moved by `offset`.
+    fn moved(&mut self, offset: [i64; 2]) {
+        self.cam.pos += DVec2::new(offset[0] as f64, offset[1] as f64);
+        self.shifted(offset);
+    }
+    /// Moves the probes along with the contents of the grid, drops the
+    /// selection and lets go of the pressed button at its new position.
+    fn shifted(&mut self, offset: [i64; 2]) {
+        self.waveform.shift(offset, &self.grid);
+        self.selection = None;
+        if let Some([x, y]) = self.pressed.take() {
+            let (x, y) = (x as i64 + offset[0], y as i64 + offset[1]);
+            let (x, y) = (x as usize, y as usize);
+            if let Some(Cell::Button { .. }) = self.grid.get(x, y) {
+                self.grid.set_active(x, y, false);
+            }
+        }
+    }
+    /// The edge of the grid closest to the mouse.
+    fn nearest_edge(&self) -> Direction {
+        let mouse_pos = self.cam.screen_to_world(self.mouse_pos);
+        let size = DVec2::new(self.grid.width() as f64, self.grid.height() as f64);
+        [
+            (Direction::Up, mouse_pos.y),
+            (Direction::Down, size.y - mouse_pos.y),
+            (Direction::Left, mouse_pos.x),
+            (Direction::Right, size.x - mouse_pos.x),
+        ]
+        .into_iter()
+        .min_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()))
+        .unwrap()
+        .0
+    }
+    /// Changes the cell in the hand, or the one under the mouse if the hand
+    /// is empty.
+    fn edit_hand_or_hovered(&mut self, f: impl FnOnce(&mut Cell)) {
+        if self.hand != Cell::Empty {
+            f(&mut self.hand);
+            return;
+        }
+        let Some([x, y]) = self.hovered() else {
+            return;
+        };
+        let Some(&cell) = self.grid.get(x, y) else {
+            return;
+        };
+        let mut edited = cell;
+        f(&mut edited);
+        // only real changes count as an edit, and cells of instances only
+        // change with their module
+        if edited != cell {
+            if let Some(cell) = self.grid.get_mut(x, y) {
+                *cell = edited;
+            }
+        }
+    }
+    /// Changes the parameter of the cell in the hand or under the mouse: the
+    /// delay of delays and the period of clocks, with shift held the high
+    /// time and with control held the phase of clocks.
+    fn adjust(&mut self, delta: i8) {
+        let shift = self.keys[Key::Shift];
+        let control = self.keys[Key::Control];
+        self.edit_hand_or_hovered(|cell| match *cell {
+            Cell::Delay { ticks, .. } => cell.set_delay(ticks.saturating_add_signed(delta)),
+            Cell::Clock {
+                period,
+                phase,
+                high,
+                ..
+            } => {
+                if shift {
+                    cell.set_clock(period, phase, high.saturating_add_signed(delta));
+                } else if control {
+                    let phase = (phase as i16 + delta as i16).rem_euclid(period as i16);
+                    cell.set_clock(period, phase as u8, high);
+                } else {
+                    cell.set_clock(period.saturating_add_signed(delta), phase, high);
+                }
+            }
+            _ => {}
+        });
+    }
     fn make_active(&mut self, f: impl Fn(bool) -> bool) {
-        let mouse_pos = self.cam.screen_to_world(self.mouse_pos).floor().as_uvec2();
-        if mouse_pos.x < self.grid.width() as u32 && mouse_pos.y < self.grid.height() as u32 {
-            let cell = &mut self.grid[[mouse_pos.x as usize, mouse_pos.y as usize]];
-            match &*cell {
-                Cell::Point { .. } => return,
-                _ => {}
+        if let Some([x, y]) = self.hovered() {
+            let cell = self.grid[[x, y]];
+            if matches!(cell, Cell::Point { .. }) || self.grid.instance_at(x, y).is_some() {
+                return;
             }
-            cell.set(f(cell.is_active()));
+            self.grid.set_active(x, y, f(cell.is_active()));
         }
     }
 }
@@ -0,0 +1,237 @@
+use crate::grid::{Grid, PinKind, Settle};
+use std::fmt::Write;
+
+/// The outputs of a circuit for every combination of its inputs, found by
+/// settling a copy of the grid for each of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TruthTable {
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    /// One row per combination, counting up in binary with the first input
+    /// as the highest bit.
+    pub rows: Vec<Row>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    pub inputs: Vec<bool>,
+    /// `None` for outputs that never settle.
+    pub outputs: Vec<Option<bool>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TruthTableError {
+    /// There is no input pin with this name.
+    UnknownInput(String),
+    /// There is no output pin with this name.
+    UnknownOutput(String),
+    /// More than [`TruthTable::MAX_INPUTS`] inputs.
+    TooManyInputs(usize),
+}
+
+impl TruthTable {
+    pub const MAX_INPUTS: usize = 16;
+
+    /// Sets the input pins to every combination, lets the grid settle for at
+    /// most `max_ticks` ticks with [`Grid::simulate`] and reads the output
+    /// pins. Every combination starts from the state the grid is in, which
+    /// matters for sequential circuits. Outputs that keep changing, or all of
+    /// them if the grid doesn't repeat its state within the tick limit, are
+    /// reported as `None`.
+    pub fn generate(
+        grid: &Grid,
+        inputs: &[&str],
+        outputs: &[&str],
+        max_ticks: u64,
+    ) -> Result<Self, TruthTableError> {
+        if let Some(name) = inputs
+            .iter()
+            .find(|name| pins(grid, name, PinKind::In).is_empty())
+        {
+            return Err(TruthTableError::UnknownInput(name.to_string()));
+        }
+        if let Some(name) = outputs
+            .iter()
+            .find(|name| pins(grid, name, PinKind::Out).is_empty())
+        {
+            return Err(TruthTableError::UnknownOutput(name.to_string()));
+        }
+        if inputs.len() > Self::MAX_INPUTS {
+            return Err(TruthTableError::TooManyInputs(inputs.len()));
+        }
+        let rows = (0..1usize << inputs.len())
+            .map(|combination| {
+                let values: Vec<bool> = (0..inputs.len())
+                    .map(|i| combination >> (inputs.len() - 1 - i) & 1 == 1)
+                    .collect();
+                let outputs = settled_outputs(grid, inputs, &values, outputs, max_ticks);
+                Row {
+                    inputs: values,
+                    outputs,
+                }
+            })
+            .collect();
+        Ok(Self {
+            inputs: inputs.iter().map(|name| name.to_string()).collect(),
+            outputs: outputs.iter().map(|name| name.to_string()).collect(),
+            rows,
+        })
+    }
+    /// Like [`TruthTable::generate`] with all input and output pins of the
+    /// grid, in the order they are first found row by row.
+    pub fn of_pins(grid: &Grid, max_ticks: u64) -> Result<Self, TruthTableError> {
+        let inputs = pin_names(grid, PinKind::In);
+        let outputs = pin_names(grid, PinKind::Out);
+        Self::generate(grid, &inputs, &outputs, max_ticks)
+    }
+    /// The rows with outputs that never settle.
+    pub fn unsettled(&self) -> impl Iterator<Item = &Row> {
+        self.rows
+            .iter()
+            .filter(|row| row.outputs.iter().any(Option::is_none))
+    }
+    /// The header and the rows as text, outputs that never settle are `x`.
+    fn cells(&self) -> Vec<Vec<String>> {
+        let value = |value: Option<bool>| match value {
+            None => String::from("x"),
+            Some(active) => (active as u8).to_string(),
+        };
+        let header = self.inputs.iter().chain(&self.outputs).cloned().collect();
+        let rows = self.rows.iter().map(|row| {
+            (row.inputs.iter().map(|active| value(Some(*active))))
+                .chain(row.outputs.iter().map(|active| value(*active)))
+                .collect()
+        });
+        std::iter::once(header).chain(rows).collect()
+    }
+    /// Comma separated values with a header line, names are quoted if they
+    /// need to be.
+    pub fn csv(&self) -> String {
+        let mut csv = String::new();
+        for line in self.cells() {
+            let fields: Vec<String> = line
+                .into_iter()
+                .map(|field| match field.contains([',', '"', '\n']) {
+                    true => format!("\"{}\"", field.replace('"', "\"\"")),
+                    false => field,
+                })
+                .collect();
+            writeln!(csv, "{}", fields.join(",")).unwrap();
+        }
+        csv
+    }
+    /// A Markdown table, outputs that never settle are `x`.
+    pub fn markdown(&self) -> String {
+        let mut markdown = String::new();
+        for (i, line) in self.cells().iter().enumerate() {
+            let line: Vec<String> = line.iter().map(|field| field.replace('|', "\\|")).collect();
+            writeln!(markdown, "| {} |", line.join(" | ")).unwrap();
+            if i == 0 {
+                writeln!(markdown, "|{}", "---|".repeat(line.len())).unwrap();
+            }
+        }
+        markdown
+    }
+}
+
+/// The names of the pins of a kind, in the order they are first found row by
+/// row.
+pub(crate) fn pin_names(grid: &Grid, kind: PinKind) -> Vec<&str> {
+    let mut names: Vec<&str> = Vec::new();
+    for (_, k, name) in grid.pins() {
+        if k == kind && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+fn pins(grid: &Grid, name: &str, kind: PinKind) -> Vec<[usize; 2]> {
+    grid.pins()
+        .filter(|(_, k, n)| *k == kind && *n == name)
+        .map(|(pos, _, _)| pos)
+        .collect()
+}
+
+/// Settles a copy of the grid with the input pins set to the values and
+/// reads the output pins, `None` for the ones that never settle.
+pub(crate) fn settled_outputs(
+    grid: &Grid,
+    inputs: &[&str],
+    values: &[bool],
+    outputs: &[&str],
+    max_ticks: u64,
+) -> Vec<Option<bool>> {
+    let mut grid = grid.clone();
+    for (name, active) in inputs.iter().zip(values) {
+        grid.set_input(name, *active);
+    }
+    let settle = grid.settle_with(max_ticks, Grid::simulate);
+    outputs
+        .iter()
+        .map(|name| match &settle {
+            Settle::Stable { .. } => grid.output(name),
+            Settle::Oscillating { cells, .. }
+                if !pins(&grid, name, PinKind::Out)
+                    .iter()
+                    .any(|pin| cells.contains(pin)) =>
+            {
+                grid.output(name)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn truth_table_of_pins() {
+    use crate::grid::{Cell, Direction};
+    let not = Cell::Not {
+        active: false,
+        direction: Direction::Right,
+    };
+    let mut grid = Grid::new(3, 3);
+    for (y, (input, output)) in [("a", "y"), ("b", "z")].into_iter().enumerate() {
+        let input = grid.pin(input, PinKind::In, Direction::Right).unwrap();
+        let output = grid.pin(output, PinKind::Out, Direction::Right).unwrap();
+        grid.place(0, y, input);
+        grid.place(1, y, not);
+        grid.place(2, y, output);
+    }
+    grid.place(
+        1,
+        2,
+        Cell::Clock {
+            active: false,
+            direction: Direction::Right,
+            period: 2,
+            phase: 0,
+            high: 1,
+        },
+    );
+    let clock = grid.pin("clock", PinKind::Out, Direction::Right).unwrap();
+    grid.place(2, 2, clock);
+
+    let table = TruthTable::of_pins(&grid, 100).unwrap();
+    assert_eq!(table.inputs, ["a", "b"]);
+    assert_eq!(table.outputs, ["y", "z", "clock"]);
+    assert_eq!(table.unsettled().count(), 4);
+    assert_eq!(
+        table.csv(),
+        "a,b,y,z,clock\n0,0,1,1,x\n0,1,1,0,x\n1,0,0,1,x\n1,1,0,0,x\n"
+    );
+    assert!(table
+        .markdown()
+        .starts_with("| a | b | y | z | clock |\n|---|---|---|---|---|\n| 0 | 0 | 1 | 1 | x |\n"));
+
+    let table = TruthTable::generate(&grid, &["b"], &["z"], 100).unwrap();
+    assert_eq!(table.unsettled().count(), 0);
+    assert_eq!(
+        TruthTable::generate(&grid, &["y"], &["z"], 100),
+        Err(TruthTableError::UnknownInput(String::from("y")))
+    );
+    assert_eq!(
+        TruthTable::generate(&grid, &[], &["a"], 100),
+        Err(TruthTableError::UnknownOutput(String::from("a")))
+    );
+}
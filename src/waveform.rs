@@ -0,0 +1,228 @@
+use crate::grid::Grid;
+use std::fmt::Write;
+
+/// A cell whose signal is recorded by a [`Waveform`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Probe {
+    pub name: String,
+    pub pos: [usize; 2],
+}
+
+/// The signals of the probed cells on every sampled tick.
+///
+/// Call [`Waveform::sample`] after every tick and write the result as a
+/// Value Change Dump with [`Waveform::vcd`] to look at it in a waveform
+/// viewer like GTKWave.
+#[derive(Debug, Clone, Default)]
+pub struct Waveform {
+    probes: Vec<Probe>,
+    /// The sampled ticks in increasing order.
+    ticks: Vec<u64>,
+    /// The samples of every probe, `None` before the probe was added.
+    values: Vec<Vec<Option<bool>>>,
+}
+
+impl Waveform {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Starts recording the cell at the given position, returns `false` if it
+    /// is already probed.
+    pub fn add_probe(&mut self, name: &str, pos: [usize; 2]) -> bool {
+        if self.probe_at(pos).is_some() {
+            return false;
+        }
+        self.probes.push(Probe {
+            name: name.to_string(),
+            pos,
+        });
+        self.values.push(vec![None; self.ticks.len()]);
+        true
+    }
+    /// Stops recording the cell at the given position and drops its samples.
+    pub fn remove_probe(&mut self, pos: [usize; 2]) -> Option<Probe> {
+        let probe = self.probe_at(pos)?;
+        self.values.remove(probe);
+        Some(self.probes.remove(probe))
+    }
+    pub fn probe_at(&self, pos: [usize; 2]) -> Option<usize> {
+        self.probes.iter().position(|probe| probe.pos == pos)
+    }
+    pub fn probes(&self) -> &[Probe] {
+        &self.probes
+    }
+    /// Moves the probes along with the contents of the grid, see
+    /// [`Grid::shift`]. Probes moved off the grid are removed.
+    pub fn shift(&mut self, offset: [i64; 2], grid: &Grid) {
+        for probe in (0..self.probes.len()).rev() {
+            let [x, y] = self.probes[probe].pos;
+            let (x, y) = (x as i64 + offset[0], y as i64 + offset[1]);
+            match x >= 0 && y >= 0 && grid.get(x as usize, y as usize).is_some() {
+                true => self.probes[probe].pos = [x as usize, y as usize],
+                false => {
+                    self.probes.remove(probe);
+                    self.values.remove(probe);
+                }
+            }
+        }
+    }
+    /// Records the signals of the probed cells at the current tick of the
+    /// grid. Samples of the same or later ticks are replaced, so recording
+    /// continues correctly after going back in time.
+    pub fn sample(&mut self, grid: &Grid) {
+        let tick = grid.tick();
+        let keep = self.ticks.partition_point(|&t| t < tick);
+        self.ticks.truncate(keep);
+        self.ticks.push(tick);
+        for (probe, values) in self.probes.iter().zip(&mut self.values) {
+            values.truncate(keep);
+            let [x, y] = probe.pos;
+            values.push(grid.get(x, y).map(|cell| cell.is_active()));
+        }
+    }
+    /// The samples of a probe together with their ticks.
+    pub fn samples(&self, probe: usize) -> impl Iterator<Item = (u64, Option<bool>)> + '_ {
+        self.ticks
+            .iter()
+            .copied()
+            .zip(self.values[probe].iter().copied())
+    }
+    /// Like [`Waveform::samples`], but only from the last sample at or before
+    /// `from` up to the last one at or before `to`.
+    pub fn samples_between(
+        &self,
+        probe: usize,
+        from: u64,
+        to: u64,
+    ) -> impl Iterator<Item = (u64, Option<bool>)> + '_ {
+        let first = self.ticks.partition_point(|&t| t <= from).saturating_sub(1);
+        let last = self.ticks.partition_point(|&t| t <= to).max(first);
+        self.ticks[first..last]
+            .iter()
+            .copied()
+            .zip(self.values[probe][first..last].iter().copied())
+    }
+    /// The signal of a probe at a tick, which is the last sample at or before
+    /// it.
+    pub fn value_at(&self, probe: usize, tick: u64) -> Option<bool> {
+        let i = self.ticks.partition_point(|&t| t <= tick).checked_sub(1)?;
+        self.values[probe][i]
+    }
+    /// The first and last sampled tick.
+    pub fn ticks(&self) -> Option<[u64; 2]> {
+        Some([*self.ticks.first()?, *self.ticks.last()?])
+    }
+    /// Drops all samples but keeps the probes.
+    pub fn clear(&mut self) {
+        self.ticks.clear();
+        for values in &mut self.values {
+            values.clear();
+        }
+    }
+    /// The samples as a Value Change Dump, one tick is one nanosecond.
+    /// Samples before a probe was added are unknown.
+    pub fn vcd(&self) -> String {
+        let mut vcd = String::new();
+        let ids: Vec<String> = (0..self.probes.len()).map(vcd_id).collect();
+        writeln!(vcd, "$version circuits3 {} $end", env!("CARGO_PKG_VERSION")).unwrap();
+        writeln!(vcd, "$timescale 1ns $end").unwrap();
+        writeln!(vcd, "$scope module grid $end").unwrap();
+        for (probe, id) in self.probes.iter().zip(&ids) {
+            // names can't contain whitespace
+            let name: String = probe
+                .name
+                .chars()
+                .map(|c| if c.is_whitespace() { '_' } else { c })
+                .collect();
+            writeln!(vcd, "$var wire 1 {id} {name} $end").unwrap();
+        }
+        writeln!(vcd, "$upscope $end").unwrap();
+        writeln!(vcd, "$enddefinitions $end").unwrap();
+        let value = |value: Option<bool>| match value {
+            None => 'x',
+            Some(false) => '0',
+            Some(true) => '1',
+        };
+        for (i, tick) in self.ticks.iter().enumerate() {
+            let changes: Vec<(&String, Option<bool>)> = ids
+                .iter()
+                .zip(&self.values)
+                .filter(|(_, values)| i == 0 || values[i] != values[i - 1])
+                .map(|(id, values)| (id, values[i]))
+                .collect();
+            if changes.is_empty() && i + 1 < self.ticks.len() {
+                continue;
+            }
+            // the last tick is written anyway to show how long the recording
+            // went on after the last change
+            writeln!(vcd, "#{tick}").unwrap();
+            if i == 0 {
+                writeln!(vcd, "$dumpvars").unwrap();
+            }
+            for (id, active) in changes {
+                writeln!(vcd, "{}{id}", value(active)).unwrap();
+            }
+            if i == 0 {
+                writeln!(vcd, "$end").unwrap();
+            }
+        }
+        vcd
+    }
+}
+
+/// A short identifier made of printable characters for the nth variable.
+fn vcd_id(mut n: usize) -> String {
+    let mut id = String::new();
+    loop {
+        id.push(char::from(b'!' + (n % 94) as u8));
+        n /= 94;
+        if n == 0 {
+            return id;
+        }
+        n -= 1;
+    }
+}
+
+#[test]
+fn waveform_vcd() {
+    use crate::grid::{Cell, Direction};
+    let mut grid = Grid::new(2, 1);
+    grid[[0, 0]] = Cell::Clock {
+        active: false,
+        direction: Direction::Right,
+        period: 2,
+        phase: 0,
+        high: 1,
+    };
+    grid[[1, 0]] = Cell::Lamp { active: false };
+    let mut waveform = Waveform::new();
+    assert!(waveform.add_probe("lamp", [1, 0]));
+    assert!(!waveform.add_probe("again", [1, 0]));
+    waveform.sample(&grid);
+    for _ in 0..4 {
+        grid.simulate();
+        waveform.sample(&grid);
+    }
+    waveform.add_probe("clock out", [0, 0]);
+    grid.simulate();
+    waveform.sample(&grid);
+    let lamp: Vec<bool> = waveform.samples(0).map(|(_, v)| v.unwrap()).collect();
+    assert_eq!(lamp, [false, false, false, true, false, true]);
+    assert_eq!(waveform.samples(1).filter(|(_, v)| v.is_none()).count(), 5);
+    let between: Vec<u64> = waveform.samples_between(0, 2, 3).map(|(t, _)| t).collect();
+    assert_eq!(between, [2, 3]);
+    assert_eq!(waveform.value_at(0, 3), Some(true));
+    assert_eq!(waveform.value_at(0, 100), Some(true));
+
+    let vcd = waveform.vcd();
+    assert!(vcd.contains("$var wire 1 ! lamp $end"));
+    assert!(vcd.contains("$var wire 1 \" clock_out $end"));
+    assert!(vcd.ends_with("#0\n$dumpvars\n0!\nx\"\n$end\n#3\n1!\n#4\n0!\n#5\n1!\n0\"\n"));
+
+    // going back in time replaces the later samples
+    grid.restore(&[([1, 0], Cell::Lamp { active: true })], &[], 2);
+    waveform.sample(&grid);
+    assert_eq!(waveform.ticks(), Some([0, 2]));
+    assert_eq!(vcd_id(93), "~");
+    assert_eq!(vcd_id(94), "!!");
+}